@@ -2,7 +2,10 @@ use bevy::{
     input::{keyboard::KeyboardInput, ButtonState},
     prelude::*,
 };
-use bevy_jfa::{CameraOutline, Outline, OutlinePlugin, OutlineSettings, OutlineStyle};
+use bevy_jfa::{
+    CameraOutline, Outline, OutlineMethod, OutlinePlugin, OutlineSettings, OutlineStyle,
+    OutlineTransition,
+};
 
 #[derive(Clone, Debug, Component)]
 struct RotationAxis(Vec3);
@@ -29,7 +32,8 @@ fn setup(
             ..Default::default()
         })
         .insert(RotationAxis(Vec3::Y))
-        .insert(Outline { enabled: true });
+        .insert(Outline { enabled: true })
+        .insert(OutlineTransition::default());
 
     commands
         .spawn(PbrBundle {
@@ -62,7 +66,10 @@ fn setup(
             style: outline_styles.add(OutlineStyle {
                 color: Color::hex("b4a2c8").unwrap(),
                 width: 33.0,
+                contour_width: 2.0,
+                ..default()
             }),
+            ..default()
         });
 
     commands.spawn(PointLightBundle {
@@ -86,19 +93,42 @@ fn rotate_cube(time: Res<Time>, mut query: Query<(&mut Transform, &RotationAxis)
     }
 }
 
-fn handle_keys(mut settings: ResMut<OutlineSettings>, mut keys: EventReader<KeyboardInput>) {
+fn handle_keys(
+    mut settings: ResMut<OutlineSettings>,
+    mut keys: EventReader<KeyboardInput>,
+    mut fading: Query<&mut Outline, With<OutlineTransition>>,
+) {
     for ev in keys.iter() {
+        if ev.key_code == Some(KeyCode::F) && ev.state == ButtonState::Pressed {
+            for mut outline in fading.iter_mut() {
+                outline.enabled = !outline.enabled;
+            }
+        }
+
         if ev.key_code == Some(KeyCode::R) && ev.state == ButtonState::Pressed {
             let old = settings.half_resolution();
             settings.set_half_resolution(!old);
         }
+
+        if ev.key_code == Some(KeyCode::S) && ev.state == ButtonState::Pressed {
+            let old = settings.separate_objects();
+            settings.set_separate_objects(!old);
+        }
+
+        if ev.key_code == Some(KeyCode::H) && ev.state == ButtonState::Pressed {
+            let method = match settings.method() {
+                OutlineMethod::JumpFlood => OutlineMethod::Hull,
+                OutlineMethod::Hull => OutlineMethod::JumpFlood,
+            };
+            settings.set_method(method);
+        }
     }
 }
 
 fn main() {
     App::new()
         .add_plugins(DefaultPlugins)
-        .add_plugin(OutlinePlugin)
+        .add_plugin(OutlinePlugin::default())
         // .insert_resource(Msaa::Off)
         .add_startup_system(setup)
         .add_system(rotate_cube)
@@ -0,0 +1,377 @@
+//! Bakes distance fields from images on the CPU.
+//!
+//! Run `bevy_jfa --help` for usage.
+
+use std::{error::Error, path::PathBuf, process::ExitCode, str::FromStr};
+
+use bevy_jfa::cpu::{self, Mask, SeedMap};
+use image::{DynamicImage, ImageBuffer, Luma, Rgba};
+
+const HELP: &str = "\
+Bakes a distance field from an image using the jump flooding algorithm.
+
+USAGE:
+    bevy_jfa [OPTIONS] <INPUT> <OUTPUT>
+
+ARGS:
+    <INPUT>     Source image. The selected channel is used as coverage.
+    <OUTPUT>    Output PNG.
+
+OPTIONS:
+    --spread <PIXELS>       Distance in pixels mapped to the full output range [default: 8]
+    --channel <CHANNEL>     Coverage channel: r, g, b, a or luma [default: a]
+    --sign <SIGN>           Distances to encode: outside, inside or signed [default: signed]
+    --format <FORMAT>       Output contents: sdf or seeds [default: sdf]
+    --depth <BITS>          Bits per output channel: 8 or 16 [default: 8]
+    -h, --help              Prints this message
+
+With `--format sdf`, outside distances map from 1 at the edge to 0 at the
+spread, inside distances from 0 at the edge to 1 at the spread, and signed
+distances map the edge to 0.5 with the inside above it.
+
+With `--format seeds`, the red and green channels store the normalized position
+of the nearest seed, and alpha is zero where no seed is within the spread.
+";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Channel {
+    Red,
+    Green,
+    Blue,
+    Alpha,
+    Luma,
+}
+
+impl FromStr for Channel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "r" => Ok(Channel::Red),
+            "g" => Ok(Channel::Green),
+            "b" => Ok(Channel::Blue),
+            "a" => Ok(Channel::Alpha),
+            "luma" => Ok(Channel::Luma),
+            _ => Err(format!("unknown channel `{s}`")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Sign {
+    Outside,
+    Inside,
+    Signed,
+}
+
+impl FromStr for Sign {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "outside" => Ok(Sign::Outside),
+            "inside" => Ok(Sign::Inside),
+            "signed" => Ok(Sign::Signed),
+            _ => Err(format!("unknown sign `{s}`")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Sdf,
+    Seeds,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sdf" => Ok(Format::Sdf),
+            "seeds" => Ok(Format::Seeds),
+            _ => Err(format!("unknown format `{s}`")),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Args {
+    input: PathBuf,
+    output: PathBuf,
+    spread: f32,
+    channel: Channel,
+    sign: Sign,
+    format: Format,
+    depth: u32,
+}
+
+fn parse_args(mut args: pico_args::Arguments) -> Result<Option<Args>, Box<dyn Error>> {
+    if args.contains(["-h", "--help"]) {
+        return Ok(None);
+    }
+
+    let spread: f32 = args.opt_value_from_str("--spread")?.unwrap_or(8.0);
+    if spread.is_nan() || spread <= 0.0 {
+        return Err("spread must be positive".into());
+    }
+
+    let depth = args.opt_value_from_str("--depth")?.unwrap_or(8);
+    if depth != 8 && depth != 16 {
+        return Err("depth must be 8 or 16".into());
+    }
+
+    let parsed = Args {
+        spread,
+        depth,
+        channel: args
+            .opt_value_from_str("--channel")?
+            .unwrap_or(Channel::Alpha),
+        sign: args.opt_value_from_str("--sign")?.unwrap_or(Sign::Signed),
+        format: args.opt_value_from_str("--format")?.unwrap_or(Format::Sdf),
+        input: args.free_from_str()?,
+        output: args.free_from_str()?,
+    };
+
+    let rest = args.finish();
+    if !rest.is_empty() {
+        return Err(format!("unexpected arguments: {rest:?}").into());
+    }
+
+    Ok(Some(parsed))
+}
+
+fn load_mask(args: &Args) -> Result<Mask, Box<dyn Error>> {
+    let image = image::open(&args.input)?.into_rgba32f();
+
+    let coverage = image
+        .pixels()
+        .map(|&Rgba([r, g, b, a])| match args.channel {
+            Channel::Red => r,
+            Channel::Green => g,
+            Channel::Blue => b,
+            Channel::Alpha => a,
+            Channel::Luma => 0.2126 * r + 0.7152 * g + 0.0722 * b,
+        })
+        .map(|value| value.clamp(0.0, 1.0))
+        .collect();
+
+    Ok(Mask::from_coverage(image.width(), image.height(), coverage))
+}
+
+/// Floods the outside and inside of the mask, as needed for `sign`.
+fn flood(mask: &Mask, args: &Args) -> (Option<SeedMap>, Option<SeedMap>) {
+    let outside = (args.sign != Sign::Inside).then(|| cpu::jump_flood(mask, args.spread));
+
+    let inside = (args.sign != Sign::Outside).then(|| {
+        let mut inverted = mask.clone();
+        for y in 0..mask.height() {
+            for x in 0..mask.width() {
+                inverted.set(x, y, 1.0 - mask.get(x, y));
+            }
+        }
+        cpu::jump_flood(&inverted, args.spread)
+    });
+
+    (outside, inside)
+}
+
+/// Returns the output values in `[0, 1]` for each pixel, as RGBA.
+fn bake(mask: &Mask, args: &Args) -> Vec<[f32; 4]> {
+    let (outside, inside) = flood(mask, args);
+    let (width, height) = (mask.width() as f32, mask.height() as f32);
+
+    let mut out = Vec::with_capacity((mask.width() * mask.height()) as usize);
+    for y in 0..mask.height() {
+        for x in 0..mask.width() {
+            // Beyond the spread, seeds may be missing.
+            let distance = |seeds: &Option<SeedMap>| {
+                seeds
+                    .as_ref()
+                    .and_then(|seeds| seeds.distance(x, y))
+                    .unwrap_or(f32::INFINITY)
+                    .min(args.spread)
+                    / args.spread
+            };
+
+            match args.format {
+                Format::Sdf => {
+                    let value = match args.sign {
+                        Sign::Outside => 1.0 - distance(&outside),
+                        Sign::Inside => distance(&inside),
+                        Sign::Signed => 0.5 + 0.5 * (distance(&inside) - distance(&outside)),
+                    };
+                    out.push([value, value, value, 1.0]);
+                }
+                Format::Seeds => {
+                    // Pixels inside the shape take their seed from the
+                    // inside, and vice versa.
+                    let seeds = match args.sign {
+                        Sign::Outside => &outside,
+                        Sign::Inside => &inside,
+                        Sign::Signed if mask.get(x, y) < 0.5 => &outside,
+                        Sign::Signed => &inside,
+                    };
+
+                    let seed = seeds.as_ref().and_then(|seeds| {
+                        seeds
+                            .get(x, y)
+                            .filter(|_| seeds.distance(x, y).unwrap() <= args.spread)
+                    });
+
+                    out.push(match seed {
+                        Some(seed) => [seed.x / width, seed.y / height, 0.0, 1.0],
+                        None => [0.0; 4],
+                    });
+                }
+            }
+        }
+    }
+
+    out
+}
+
+/// Encodes the output values as an image of the format and depth in `args`.
+fn encode(values: &[[f32; 4]], width: u32, height: u32, args: &Args) -> DynamicImage {
+    let pixel = |x: u32, y: u32| values[(y * width + x) as usize];
+
+    match (args.format, args.depth) {
+        (Format::Sdf, 8) => ImageBuffer::from_fn(width, height, |x, y| {
+            Luma([(pixel(x, y)[0] * 255.0).round() as u8])
+        })
+        .into(),
+        (Format::Sdf, _) => ImageBuffer::from_fn(width, height, |x, y| {
+            Luma([(pixel(x, y)[0] * 65535.0).round() as u16])
+        })
+        .into(),
+        (Format::Seeds, 8) => ImageBuffer::from_fn(width, height, |x, y| {
+            Rgba(pixel(x, y).map(|v| (v * 255.0).round() as u8))
+        })
+        .into(),
+        (Format::Seeds, _) => ImageBuffer::from_fn(width, height, |x, y| {
+            Rgba(pixel(x, y).map(|v| (v * 65535.0).round() as u16))
+        })
+        .into(),
+    }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let Some(args) = parse_args(pico_args::Arguments::from_env())? else {
+        print!("{HELP}");
+        return Ok(());
+    };
+
+    let mask = load_mask(&args)?;
+    let values = bake(&mask, &args);
+    encode(&values, mask.width(), mask.height(), &args).save(&args.output)?;
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Args>, Box<dyn Error>> {
+        parse_args(pico_args::Arguments::from_vec(
+            args.iter().map(Into::into).collect(),
+        ))
+    }
+
+    #[test]
+    fn parses_defaults_and_options() {
+        let args = parse(&["in.png", "out.png"]).unwrap().unwrap();
+        assert_eq!(args.input, PathBuf::from("in.png"));
+        assert_eq!(args.output, PathBuf::from("out.png"));
+        assert_eq!(args.spread, 8.0);
+        assert_eq!(args.channel, Channel::Alpha);
+        assert_eq!(args.sign, Sign::Signed);
+        assert_eq!(args.format, Format::Sdf);
+        assert_eq!(args.depth, 8);
+
+        let args = parse(&[
+            "--spread",
+            "2.5",
+            "--channel",
+            "luma",
+            "--sign",
+            "inside",
+            "--format",
+            "seeds",
+            "--depth",
+            "16",
+            "in.png",
+            "out.png",
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(args.spread, 2.5);
+        assert_eq!(args.channel, Channel::Luma);
+        assert_eq!(args.sign, Sign::Inside);
+        assert_eq!(args.format, Format::Seeds);
+        assert_eq!(args.depth, 16);
+
+        assert!(parse(&["--help"]).unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        for args in [
+            &["--spread", "0", "in.png", "out.png"][..],
+            &["--spread", "NaN", "in.png", "out.png"],
+            &["--depth", "12", "in.png", "out.png"],
+            &["--channel", "x", "in.png", "out.png"],
+            &["--sign", "both", "in.png", "out.png"],
+            &["--format", "msdf", "in.png", "out.png"],
+            &["in.png"],
+            &["in.png", "out.png", "extra.png"],
+            &["--unknown", "in.png", "out.png"],
+        ] {
+            assert!(parse(args).is_err(), "accepted {args:?}");
+        }
+    }
+
+    #[test]
+    fn encodes_format_and_depth() {
+        let values = [[0.0, 0.25, 0.0, 1.0], [1.0, 0.5, 0.0, 0.0]];
+        let args = |format, depth| Args {
+            input: PathBuf::new(),
+            output: PathBuf::new(),
+            spread: 8.0,
+            channel: Channel::Alpha,
+            sign: Sign::Signed,
+            format,
+            depth,
+        };
+
+        let DynamicImage::ImageLuma8(image) = encode(&values, 2, 1, &args(Format::Sdf, 8)) else {
+            panic!("expected an 8-bit grayscale image");
+        };
+        assert_eq!(image.into_raw(), [0, 255]);
+
+        let DynamicImage::ImageLuma16(image) = encode(&values, 2, 1, &args(Format::Sdf, 16)) else {
+            panic!("expected a 16-bit grayscale image");
+        };
+        assert_eq!(image.into_raw(), [0, 65535]);
+
+        let DynamicImage::ImageRgba8(image) = encode(&values, 2, 1, &args(Format::Seeds, 8)) else {
+            panic!("expected an 8-bit RGBA image");
+        };
+        assert_eq!(image.into_raw(), [0, 64, 0, 255, 255, 128, 0, 0]);
+
+        let DynamicImage::ImageRgba16(image) = encode(&values, 2, 1, &args(Format::Seeds, 16))
+        else {
+            panic!("expected a 16-bit RGBA image");
+        };
+        assert_eq!(image.get_pixel(1, 0).0, [65535, 32768, 0, 0]);
+    }
+}
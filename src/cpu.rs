@@ -0,0 +1,704 @@
+//! CPU reference implementation of the jump flooding passes.
+//!
+//! These functions mirror the GPU passes used for outlines: [`init`] and
+//! [`init_edges`] follow `jfa_init.wgsl`, [`jump_step`] follows `jfa.wgsl` and
+//! [`outline`] follows `outline.wgsl`. [`jump_flood`] runs the passes with the
+//! same schedule as the render graph, so the results can be used to test
+//! changes to the algorithm, or as a fallback where no GPU is available.
+//!
+//! Positions are stored in pixel coordinates, with pixel centers at
+//! half-integers. Unlike the GPU, positions are not quantized to the JFA
+//! texture format.
+
+use bevy::math::{Vec2, Vec4};
+
+use crate::{
+    jfa,
+    outline::{self, OutlineParams},
+    OutlineStyle,
+};
+
+/// Coverage image, as produced by the mask pass.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mask {
+    width: u32,
+    height: u32,
+    coverage: Vec<f32>,
+}
+
+impl Mask {
+    /// Creates an empty mask.
+    pub fn new(width: u32, height: u32) -> Mask {
+        Mask::from_coverage(width, height, vec![0.0; (width * height) as usize])
+    }
+
+    /// Creates a mask from row-major coverage values in `[0, 1]`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `coverage` doesn't contain `width * height` values.
+    pub fn from_coverage(width: u32, height: u32, coverage: Vec<f32>) -> Mask {
+        assert_eq!(coverage.len(), (width * height) as usize);
+
+        Mask {
+            width,
+            height,
+            coverage,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the coverage of the pixel at `(x, y)`.
+    pub fn get(&self, x: u32, y: u32) -> f32 {
+        self.coverage[(y * self.width + x) as usize]
+    }
+
+    /// Sets the coverage of the pixel at `(x, y)`.
+    pub fn set(&mut self, x: u32, y: u32, value: f32) {
+        self.coverage[(y * self.width + x) as usize] = value;
+    }
+
+    // Samples with clamp-to-edge addressing, like the mask sampler.
+    fn sample(&self, x: i64, y: i64) -> f32 {
+        let x = x.clamp(0, self.width as i64 - 1) as u32;
+        let y = y.clamp(0, self.height as i64 - 1) as u32;
+        self.get(x, y)
+    }
+}
+
+/// Position of the nearest seed for each pixel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SeedMap {
+    width: u32,
+    height: u32,
+    seeds: Vec<Option<Vec2>>,
+}
+
+impl SeedMap {
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the nearest seed found for the pixel at `(x, y)`, if any.
+    pub fn get(&self, x: u32, y: u32) -> Option<Vec2> {
+        self.seeds[(y * self.width + x) as usize]
+    }
+
+    /// Returns the distance from the center of the pixel at `(x, y)` to its
+    /// nearest seed, if any.
+    pub fn distance(&self, x: u32, y: u32) -> Option<f32> {
+        self.get(x, y).map(|seed| pixel_center(x, y).distance(seed))
+    }
+
+    /// Returns the row-major distance field, with `f32::INFINITY` for pixels
+    /// without a seed.
+    pub fn distance_field(&self) -> Vec<f32> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|(x, y)| self.distance(x, y).unwrap_or(f32::INFINITY))
+            .collect()
+    }
+
+    // Samples with clamp-to-edge addressing, like the JFA sampler.
+    fn sample(&self, x: i64, y: i64) -> Option<Vec2> {
+        let x = x.clamp(0, self.width as i64 - 1) as u32;
+        let y = y.clamp(0, self.height as i64 - 1) as u32;
+        self.get(x, y)
+    }
+}
+
+fn pixel_center(x: u32, y: u32) -> Vec2 {
+    Vec2::new(x as f32 + 0.5, y as f32 + 0.5)
+}
+
+/// Computes the initial seeds from a mask.
+///
+/// Fully covered pixels are seeds at their centers. Partially covered pixels
+/// are seeds offset along the Sobel gradient of the mask, for sub-pixel
+/// accuracy.
+pub fn init(mask: &Mask) -> SeedMap {
+    let mut seeds = Vec::with_capacity((mask.width * mask.height) as usize);
+
+    for y in 0..mask.height {
+        for x in 0..mask.width {
+            seeds.push(init_pixel(mask, x, y));
+        }
+    }
+
+    SeedMap {
+        width: mask.width,
+        height: mask.height,
+        seeds,
+    }
+}
+
+/// Computes the initial seeds from a mask, like [`init`], except that fully
+/// covered pixels are only seeds on the boundary of the mask.
+///
+/// After flooding, interior pixels receive the distance to the boundary. This
+/// is used when an [`OutlineFill`](crate::OutlineFill) fades out from the edge.
+pub fn init_edges(mask: &Mask) -> SeedMap {
+    let mut seeds = Vec::with_capacity((mask.width * mask.height) as usize);
+
+    for y in 0..mask.height {
+        for x in 0..mask.width {
+            let interior = mask.get(x, y) > 0.99
+                && (-1..=1)
+                    .all(|dy| (-1..=1).all(|dx| mask.sample(x as i64 + dx, y as i64 + dy) > 0.99));
+
+            seeds.push(if interior {
+                None
+            } else {
+                init_pixel(mask, x, y)
+            });
+        }
+    }
+
+    SeedMap {
+        width: mask.width,
+        height: mask.height,
+        seeds,
+    }
+}
+
+fn init_pixel(mask: &Mask, x: u32, y: u32) -> Option<Vec2> {
+    let center = pixel_center(x, y);
+    let s = |dx: i64, dy: i64| mask.sample(x as i64 + dx, y as i64 + dy);
+
+    let coverage = s(0, 0);
+    if coverage > 0.99 {
+        return Some(center);
+    }
+
+    if coverage < 0.01 {
+        return None;
+    }
+
+    let sobel_x = s(-1, -1) + 2.0 * s(-1, 0) + s(-1, 1) - s(1, -1) - 2.0 * s(1, 0) - s(1, 1);
+    let sobel_y = s(-1, -1) + 2.0 * s(0, -1) + s(1, -1) - s(-1, 1) - 2.0 * s(0, 1) - s(1, 1);
+    let dir = -Vec2::new(sobel_x, sobel_y);
+
+    if dir.x.abs() < 0.005 && dir.y.abs() < 0.005 {
+        return Some(center);
+    }
+
+    Some(center + dir.normalize() * (1.0 - coverage))
+}
+
+/// Runs a single jump flood pass with the given jump distance in pixels.
+pub fn jump_step(seeds: &SeedMap, dist: u32) -> SeedMap {
+    let dist = dist as i64;
+    let mut out = Vec::with_capacity(seeds.seeds.len());
+
+    for y in 0..seeds.height {
+        for x in 0..seeds.width {
+            let center = pixel_center(x, y);
+
+            let mut min_dist2 = f32::MAX;
+            let mut min_seed = None;
+
+            // Same sample order as the shader, so ties resolve identically.
+            for dx in [-dist, 0, dist] {
+                for dy in [-dist, 0, dist] {
+                    let Some(seed) = seeds.sample(x as i64 + dx, y as i64 + dy) else {
+                        continue;
+                    };
+
+                    let dist2 = center.distance_squared(seed);
+                    if dist2 < min_dist2 {
+                        min_dist2 = dist2;
+                        min_seed = Some(seed);
+                    }
+                }
+            }
+
+            out.push(min_seed);
+        }
+    }
+
+    SeedMap {
+        width: seeds.width,
+        height: seeds.height,
+        seeds: out,
+    }
+}
+
+/// Returns the jump distances used to flood `flood_distance` pixels in a
+/// `width` by `height` image, in the order they are run.
+pub fn jump_distances(flood_distance: f32, width: u32, height: u32) -> Vec<u32> {
+    let max_exp = jfa::max_jump_exponent(flood_distance, width as f32, height as f32);
+    (0..=max_exp).rev().map(|exp| 1 << exp).collect()
+}
+
+/// Computes the seeds for `mask` and floods them `flood_distance` pixels.
+pub fn jump_flood(mask: &Mask, flood_distance: f32) -> SeedMap {
+    jump_distances(flood_distance, mask.width, mask.height)
+        .into_iter()
+        .fold(init(mask), |seeds, dist| jump_step(&seeds, dist))
+}
+
+/// Shades the outline for `mask` with the given style.
+///
+/// Returns row-major, non-premultiplied colors to be alpha blended over the
+/// scene. If the style's fill fades out from the edge, `seeds` should be
+/// flooded from [`init_edges`]. Creases aren't drawn, as masks have no normals
+/// or depths.
+pub fn outline(mask: &Mask, seeds: &SeedMap, style: &OutlineStyle) -> Vec<Vec4> {
+    assert_eq!((mask.width, mask.height), (seeds.width, seeds.height));
+
+    let params = OutlineParams::from_style(style);
+    let rgb = params.color.truncate();
+    let transparent = Vec4::ZERO;
+
+    (0..mask.height)
+        .flat_map(|y| (0..mask.width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let mask_value = mask.get(x, y);
+
+            // Partially covered seeds are offset from the pixel center, by
+            // the distance to the edge.
+            let edge_distance = if mask_value >= 1.0 {
+                None
+            } else {
+                seeds.distance(x, y).map(|mag| {
+                    if mask_value > 0.0 && mag < 0.1 {
+                        1.0 - mask_value
+                    } else {
+                        mag
+                    }
+                })
+            };
+            let outline = match edge_distance {
+                Some(distance) => {
+                    let fade = edge_fade(&params, distance)
+                        .min(edge_fade(&params, params.weight - distance));
+                    rgb.extend(fade * params.color.w)
+                }
+                None => transparent,
+            };
+
+            // The shadow is hidden behind the objects and below their outline,
+            // and the fill covers their interior.
+            let shadow = params
+                .shadow_color
+                .truncate()
+                .extend(shadow_alpha(mask, seeds, &params, x, y) * (1.0 - mask_value));
+            let fill = params
+                .fill_color
+                .truncate()
+                .extend(fill_alpha(seeds, &params, x, y) * mask_value);
+
+            blend_over(outline, blend_over(fill, shadow))
+        })
+        .collect()
+}
+
+// Returns the opacity of an edge `distance` pixels away, fading out over the
+// softness of the outline.
+fn edge_fade(params: &OutlineParams, distance: f32) -> f32 {
+    (distance / params.softness.max(0.0001)).clamp(0.0, 1.0)
+}
+
+// Returns the shadow opacity at `(x, y)`, ignoring the objects casting it.
+fn shadow_alpha(mask: &Mask, seeds: &SeedMap, params: &OutlineParams, x: u32, y: u32) -> f32 {
+    if !params.has_shadow() {
+        return 0.0;
+    }
+
+    let coord = pixel_center(x, y) - params.shadow_offset;
+    let size = Vec2::new(seeds.width as f32, seeds.height as f32);
+    if coord.cmplt(Vec2::ZERO).any() || coord.cmpgt(size).any() {
+        return 0.0;
+    }
+
+    // Interior pixels may not be seeds, when the fill needs the distance to
+    // the edge.
+    let texel = coord.floor().min(size - 1.0);
+    if mask.get(texel.x as u32, texel.y as u32) >= 1.0 {
+        return params.shadow_color.w;
+    }
+
+    let Some(seed) = seeds.get(texel.x as u32, texel.y as u32) else {
+        return 0.0;
+    };
+
+    let mag = coord.distance(seed);
+    let fade = 1.0 - smoothstep(0.0, outline::shadow_reach(params.shadow_softness), mag);
+    params.shadow_color.w * fade
+}
+
+// Returns the coverage of the fill pattern at `coord`.
+fn fill_pattern(params: &OutlineParams, coord: Vec2) -> f32 {
+    let spacing = params.fill_pattern_spacing.max(1.0);
+    let fract = |v: f32| v - v.floor();
+
+    match params.fill_pattern {
+        // Diagonal stripes.
+        1 => {
+            let along = (coord.x + coord.y) * std::f32::consts::FRAC_1_SQRT_2;
+            let from_center = (fract(along / spacing) - 0.5).abs() * spacing;
+            (0.5 * params.fill_pattern_size - from_center + 0.5).clamp(0.0, 1.0)
+        }
+        // Dots.
+        2 => {
+            let cell = coord / spacing;
+            let from_center = (cell - cell.floor() - 0.5) * spacing;
+            (params.fill_pattern_size - from_center.length() + 0.5).clamp(0.0, 1.0)
+        }
+        _ => 1.0,
+    }
+}
+
+// Returns the fill opacity at `(x, y)`, ignoring the coverage of the mask.
+fn fill_alpha(seeds: &SeedMap, params: &OutlineParams, x: u32, y: u32) -> f32 {
+    if params.fill_color.w <= 0.0 {
+        return 0.0;
+    }
+
+    let coord = pixel_center(x, y);
+    let mut alpha = params.fill_color.w * fill_pattern(params, coord);
+
+    // Interior pixels receive the distance to the edge of the mask.
+    if params.fill_inner_distance > 0.0 {
+        if let Some(mag) = seeds.distance(x, y) {
+            alpha *= 1.0 - smoothstep(0.0, params.fill_inner_distance, mag);
+        }
+    }
+
+    alpha
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// Composites non-premultiplied `top` over `bottom`.
+fn blend_over(top: Vec4, bottom: Vec4) -> Vec4 {
+    let alpha = top.w + bottom.w * (1.0 - top.w);
+    if alpha <= 0.0 {
+        return Vec4::ZERO;
+    }
+
+    let rgb = top.truncate() * top.w + bottom.truncate() * bottom.w * (1.0 - top.w);
+    (rgb / alpha).extend(alpha)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Color;
+
+    use super::*;
+    use crate::{OutlineFill, OutlineShadow};
+
+    // Small xorshift generator, so the tests are deterministic.
+    struct Rng(u32);
+
+    impl Rng {
+        fn next(&mut self) -> u32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            self.0
+        }
+
+        fn below(&mut self, n: u32) -> u32 {
+            self.next() % n
+        }
+    }
+
+    // Random binary mask made of rectangles and disks.
+    fn random_mask(rng: &mut Rng, width: u32, height: u32, shapes: u32) -> Mask {
+        let mut mask = Mask::new(width, height);
+
+        for _ in 0..shapes {
+            let cx = rng.below(width) as i64;
+            let cy = rng.below(height) as i64;
+            let r = 1 + rng.below(6) as i64;
+            let disk = rng.below(2) == 0;
+
+            for y in 0..height {
+                for x in 0..width {
+                    let (dx, dy) = (x as i64 - cx, y as i64 - cy);
+                    let inside = if disk {
+                        dx * dx + dy * dy <= r * r
+                    } else {
+                        dx.abs() <= r && dy.abs() <= r / 2
+                    };
+
+                    if inside {
+                        mask.set(x, y, 1.0);
+                    }
+                }
+            }
+        }
+
+        mask
+    }
+
+    // Exact distance from each pixel center to the nearest covered pixel center.
+    fn exact_edt(mask: &Mask) -> Vec<f32> {
+        let covered: Vec<Vec2> = (0..mask.height)
+            .flat_map(|y| (0..mask.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| mask.get(x, y) > 0.99)
+            .map(|(x, y)| pixel_center(x, y))
+            .collect();
+
+        (0..mask.height)
+            .flat_map(|y| (0..mask.width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let center = pixel_center(x, y);
+                covered
+                    .iter()
+                    .map(|seed| center.distance(*seed))
+                    .fold(f32::INFINITY, f32::min)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn init_seeds_binary_mask() {
+        let mut mask = Mask::new(4, 4);
+        mask.set(1, 2, 1.0);
+
+        let seeds = init(&mask);
+        assert_eq!(seeds.get(1, 2), Some(Vec2::new(1.5, 2.5)));
+        assert_eq!(seeds.get(0, 0), None);
+        assert_eq!(seeds.get(2, 2), None);
+    }
+
+    #[test]
+    fn init_offsets_partial_coverage_towards_covered_side() {
+        let mut mask = Mask::new(5, 5);
+        for y in 0..5 {
+            mask.set(0, y, 1.0);
+            mask.set(1, y, 1.0);
+            mask.set(2, y, 0.25);
+        }
+
+        let seed = init(&mask).get(2, 2).unwrap();
+        assert!((seed.x - (2.5 - 0.75)).abs() < 1e-5);
+        assert!((seed.y - 2.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn jump_distances_match_flood_distance() {
+        assert_eq!(jump_distances(1.0, 64, 64), vec![1]);
+        assert_eq!(jump_distances(8.0, 64, 64), vec![8, 4, 2, 1]);
+        assert_eq!(jump_distances(33.0, 64, 64), vec![32, 16, 8, 4, 2, 1]);
+        // Limited by the image size.
+        assert_eq!(jump_distances(1000.0, 20, 10), vec![16, 8, 4, 2, 1]);
+    }
+
+    #[test]
+    fn flood_matches_exact_edt() {
+        let mut rng = Rng(0x9e37_79b9);
+
+        for _ in 0..8 {
+            let (width, height) = (48, 40);
+            let mask = random_mask(&mut rng, width, height, 6);
+            let expected = exact_edt(&mask);
+            let actual = jump_flood(&mask, width.max(height) as f32).distance_field();
+
+            let mut mismatched = 0;
+            for (expected, actual) in expected.iter().zip(&actual) {
+                if expected.is_infinite() {
+                    assert!(actual.is_infinite());
+                    continue;
+                }
+
+                // Jump flooding may pick a slightly farther seed, but never a
+                // nearer one than exists.
+                assert!(actual + 1e-4 >= *expected);
+                if actual - expected > 1e-4 {
+                    mismatched += 1;
+                    assert!(actual - expected < 1.0, "{actual} vs {expected}");
+                }
+            }
+
+            assert!(mismatched * 100 <= expected.len(), "{mismatched} errors");
+        }
+    }
+
+    #[test]
+    fn flood_covers_flood_distance() {
+        let mut rng = Rng(0x2545_f491);
+        let mask = random_mask(&mut rng, 64, 64, 3);
+        let expected = exact_edt(&mask);
+        let actual = jump_flood(&mask, 12.0).distance_field();
+
+        for (expected, actual) in expected.iter().zip(&actual) {
+            if *expected <= 12.0 {
+                assert!((actual - expected).abs() < 1.0, "{actual} vs {expected}");
+            }
+        }
+    }
+
+    #[test]
+    fn flood_follows_antialiased_edges() {
+        // Disk with supersampled coverage.
+        let (size, radius, samples) = (48, 10.0, 8);
+        let center = Vec2::splat(size as f32 / 2.0);
+
+        let mut mask = Mask::new(size, size);
+        for y in 0..size {
+            for x in 0..size {
+                let mut covered = 0;
+                for sy in 0..samples {
+                    for sx in 0..samples {
+                        let p = Vec2::new(
+                            x as f32 + (sx as f32 + 0.5) / samples as f32,
+                            y as f32 + (sy as f32 + 0.5) / samples as f32,
+                        );
+                        if p.distance(center) <= radius {
+                            covered += 1;
+                        }
+                    }
+                }
+                mask.set(x, y, covered as f32 / (samples * samples) as f32);
+            }
+        }
+
+        let seeds = jump_flood(&mask, 16.0);
+        for y in 0..size {
+            for x in 0..size {
+                let exact = pixel_center(x, y).distance(center) - radius;
+                if mask.get(x, y) == 0.0 && exact <= 16.0 {
+                    let actual = seeds.distance(x, y).unwrap();
+                    assert!((actual - exact).abs() < 0.75, "{actual} vs {exact}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn outline_fades_at_width() {
+        let mut mask = Mask::new(32, 1);
+        for x in 0..8 {
+            mask.set(x, 0, 1.0);
+        }
+
+        let style = OutlineStyle {
+            color: Color::rgba(1.0, 0.0, 0.0, 1.0),
+            width: 4.0,
+            ..Default::default()
+        };
+        let seeds = jump_flood(&mask, style.width);
+        let colors = outline(&mask, &seeds, &style);
+
+        // Inside the mask.
+        assert_eq!(colors[4], Vec4::ZERO);
+        // Within the outline.
+        assert_eq!(colors[8], Vec4::new(1.0, 0.0, 0.0, 1.0));
+        assert_eq!(colors[10], Vec4::new(1.0, 0.0, 0.0, 1.0));
+        // Past the outline.
+        assert_eq!(colors[12].w, 0.0);
+        assert_eq!(colors[20].w, 0.0);
+    }
+
+    #[test]
+    fn outline_softness_fades_both_edges() {
+        let mut mask = Mask::new(32, 1);
+        for x in 0..8 {
+            mask.set(x, 0, 1.0);
+        }
+
+        let style = OutlineStyle {
+            color: Color::rgba(1.0, 0.0, 0.0, 1.0),
+            width: 6.0,
+            softness: 3.0,
+            ..Default::default()
+        };
+        let seeds = jump_flood(&mask, style.width);
+        let colors = outline(&mask, &seeds, &style);
+
+        // Fading in from the mask, and out towards the width.
+        assert!((colors[8].w - 1.0 / 3.0).abs() < 1e-5);
+        assert_eq!(colors[10].w, 1.0);
+        assert!((colors[12].w - 1.0 / 3.0).abs() < 1e-5);
+        assert_eq!(colors[14].w, 0.0);
+
+        let hard = OutlineStyle {
+            softness: 0.0,
+            ..style
+        };
+        let colors = outline(&mask, &seeds, &hard);
+        assert_eq!(colors[8].w, 1.0);
+        assert_eq!(colors[12].w, 1.0);
+        assert_eq!(colors[13].w, 0.0);
+    }
+
+    #[test]
+    fn fill_fades_from_edge() {
+        let mut mask = Mask::new(32, 1);
+        for x in 4..28 {
+            mask.set(x, 0, 1.0);
+        }
+
+        let style = OutlineStyle {
+            width: 0.0,
+            fill: Some(OutlineFill {
+                color: Color::rgba(0.0, 1.0, 0.0, 0.5),
+                inner_distance: 8.0,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let params = OutlineParams::from_style(&style);
+        let seeds = jump_distances(params.flood_distance(false), 32, 1)
+            .into_iter()
+            .fold(init_edges(&mask), |seeds, dist| jump_step(&seeds, dist));
+        let colors = outline(&mask, &seeds, &style);
+
+        // Outside the mask.
+        assert_eq!(colors[2], Vec4::ZERO);
+        // Opaque at the edge, fading out towards the interior.
+        assert_eq!(colors[4], Vec4::new(0.0, 1.0, 0.0, 0.5));
+        assert_eq!(colors[27], Vec4::new(0.0, 1.0, 0.0, 0.5));
+        assert!(colors[8].w > 0.0 && colors[8].w < 0.5);
+        assert_eq!(colors[16].w, 0.0);
+    }
+
+    #[test]
+    fn shadow_is_offset_and_fades() {
+        let mut mask = Mask::new(32, 1);
+        for x in 0..8 {
+            mask.set(x, 0, 1.0);
+        }
+
+        let style = OutlineStyle {
+            color: Color::rgba(1.0, 0.0, 0.0, 1.0),
+            width: 2.0,
+            shadow: Some(OutlineShadow {
+                color: Color::rgba(0.0, 0.0, 1.0, 0.5),
+                offset: Vec2::new(8.0, 0.0),
+                softness: 4.0,
+            }),
+            ..Default::default()
+        };
+        let params = OutlineParams::from_style(&style);
+        let seeds = jump_flood(&mask, params.flood_distance(false));
+        let colors = outline(&mask, &seeds, &style);
+
+        // Hidden behind the mask.
+        assert_eq!(colors[4], Vec4::ZERO);
+        // The outline is drawn over the shadow.
+        assert_eq!(colors[8], Vec4::new(1.0, 0.0, 0.0, 1.0));
+        // Under the offset mask, past the outline.
+        assert_eq!(colors[12], Vec4::new(0.0, 0.0, 1.0, 0.5));
+        // Fading out past the offset mask.
+        assert!(colors[17].w > 0.0 && colors[17].w < 0.5);
+        assert_eq!(colors[21].w, 0.0);
+    }
+}
@@ -0,0 +1,135 @@
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics},
+    prelude::*,
+};
+
+use crate::{
+    stats::{self, OutlineStats},
+    timings::{self, OutlineGpuTimings, OutlinePass},
+};
+
+/// Plugin adding [`Diagnostics`] for outline rendering.
+///
+/// The measurements can be logged with Bevy's `LogDiagnosticsPlugin`. Counts
+/// are taken from [`OutlineStats`] and summed over all views. GPU times are
+/// taken from [`OutlineGpuTimings`], and are only recorded with the
+/// `gpu-timings` feature.
+#[derive(Default)]
+pub struct OutlineDiagnosticsPlugin;
+
+impl OutlineDiagnosticsPlugin {
+    /// GPU time in milliseconds spent rendering outline masks.
+    pub const MASK_TIME: DiagnosticId =
+        DiagnosticId::from_u128(208215416813389540329441097786540163271);
+    /// GPU time in milliseconds spent seeding the jump flood.
+    pub const JFA_INIT_TIME: DiagnosticId =
+        DiagnosticId::from_u128(95406722398710858460779373271498131629);
+    /// GPU time in milliseconds spent in jump flood iterations.
+    pub const JFA_TIME: DiagnosticId =
+        DiagnosticId::from_u128(309318346521766658394316981683802484113);
+    /// GPU time in milliseconds spent compositing outlines.
+    pub const OUTLINE_TIME: DiagnosticId =
+        DiagnosticId::from_u128(41271316419785102391693318574622770542);
+    /// GPU time in milliseconds spent drawing hull outlines.
+    pub const HULL_TIME: DiagnosticId =
+        DiagnosticId::from_u128(262117306655393406471620190218449326883);
+
+    /// Meshes drawn to outline masks.
+    pub const MESH_MASKS: DiagnosticId =
+        DiagnosticId::from_u128(169571906003496389616226183460531585839);
+    /// Jump flood iterations run.
+    pub const JFA_ITERATIONS: DiagnosticId =
+        DiagnosticId::from_u128(287443460431046880712409911350512616493);
+    /// Width in pixels of the largest jump flood textures.
+    pub const JFA_WIDTH: DiagnosticId =
+        DiagnosticId::from_u128(73318779930498066498839542339287839305);
+    /// Height in pixels of the largest jump flood textures.
+    pub const JFA_HEIGHT: DiagnosticId =
+        DiagnosticId::from_u128(228791626713530402262838395796432211869);
+    /// Bytes allocated for the textures of the outline passes.
+    pub const TEXTURE_BYTES: DiagnosticId =
+        DiagnosticId::from_u128(123946052693006004627431541587005925347);
+
+    fn setup_system(mut diagnostics: ResMut<Diagnostics>) {
+        for (id, name) in [
+            (Self::MASK_TIME, "outline_mask_time"),
+            (Self::JFA_INIT_TIME, "outline_jfa_init_time"),
+            (Self::JFA_TIME, "outline_jfa_time"),
+            (Self::OUTLINE_TIME, "outline_composite_time"),
+            (Self::HULL_TIME, "outline_hull_time"),
+        ] {
+            diagnostics.add(Diagnostic::new(id, name, 20).with_suffix("ms"));
+        }
+
+        for (id, name, suffix) in [
+            (Self::MESH_MASKS, "outline_mesh_masks", ""),
+            (Self::JFA_ITERATIONS, "outline_jfa_iterations", ""),
+            (Self::JFA_WIDTH, "outline_jfa_width", "px"),
+            (Self::JFA_HEIGHT, "outline_jfa_height", "px"),
+            (Self::TEXTURE_BYTES, "outline_texture_bytes", "B"),
+        ] {
+            diagnostics.add(Diagnostic::new(id, name, 20).with_suffix(suffix));
+        }
+    }
+
+    fn stats_system(mut diagnostics: ResMut<Diagnostics>, stats: Res<OutlineStats>) {
+        if !stats.is_changed() {
+            return;
+        }
+
+        diagnostics.add_measurement(Self::MESH_MASKS, || {
+            stats
+                .views
+                .iter()
+                .map(|view| view.mesh_masks)
+                .sum::<usize>() as f64
+        });
+        diagnostics.add_measurement(Self::JFA_ITERATIONS, || {
+            stats
+                .views
+                .iter()
+                .map(|view| view.jfa_iterations)
+                .sum::<usize>() as f64
+        });
+        diagnostics.add_measurement(Self::JFA_WIDTH, || stats.jfa_resolution.x as f64);
+        diagnostics.add_measurement(Self::JFA_HEIGHT, || stats.jfa_resolution.y as f64);
+        diagnostics.add_measurement(Self::TEXTURE_BYTES, || stats.texture_bytes as f64);
+    }
+
+    fn gpu_timings_system(mut diagnostics: ResMut<Diagnostics>, timings: Res<OutlineGpuTimings>) {
+        if !timings.is_changed() {
+            return;
+        }
+
+        // Times are summed over all views.
+        let ms = |filter: fn(OutlinePass) -> bool| timings.total(filter).as_secs_f64() * 1000.0;
+
+        diagnostics.add_measurement(Self::MASK_TIME, || ms(|pass| pass == OutlinePass::Mask));
+        diagnostics.add_measurement(Self::JFA_INIT_TIME, || {
+            ms(|pass| pass == OutlinePass::JfaInit)
+        });
+        diagnostics.add_measurement(Self::JFA_TIME, || {
+            ms(|pass| matches!(pass, OutlinePass::JfaTemporal | OutlinePass::Jfa(_)))
+        });
+        diagnostics.add_measurement(Self::OUTLINE_TIME, || {
+            ms(|pass| pass == OutlinePass::Outline)
+        });
+        diagnostics.add_measurement(Self::HULL_TIME, || ms(|pass| pass == OutlinePass::Hull));
+    }
+}
+
+impl Plugin for OutlineDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(Self::setup_system)
+            .add_system(
+                Self::stats_system
+                    .in_base_set(CoreSet::First)
+                    .after(stats::update_outline_stats),
+            )
+            .add_system(
+                Self::gpu_timings_system
+                    .in_base_set(CoreSet::First)
+                    .after(timings::update_gpu_timings),
+            );
+    }
+}
@@ -0,0 +1,122 @@
+use std::{fmt, sync::Mutex};
+
+use bevy::{asset::HandleId, prelude::*, render::render_resource::TextureFormat, utils::HashSet};
+
+/// Errors which cause outline rendering to be skipped.
+///
+/// None of these stop the app. Each distinct error is logged once as a
+/// warning, and the affected camera or style is skipped or sanitized.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OutlineError {
+    /// A camera's [`OutlineStyle`](crate::OutlineStyle) failed to load, or
+    /// was removed.
+    MissingStyle(HandleId),
+    /// A view renders to a texture format that can't be used as a color
+    /// attachment.
+    UnsupportedTargetFormat(TextureFormat),
+    /// A field of an [`OutlineStyle`](crate::OutlineStyle) is negative or
+    /// NaN.
+    InvalidStyleValue { field: &'static str, value: f32 },
+    /// More objects are outlined than have distinct IDs when
+    /// [separating objects](crate::OutlineSettings::set_separate_objects).
+    TooManyObjects { objects: usize, max: usize },
+}
+
+impl fmt::Display for OutlineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OutlineError::MissingStyle(id) => {
+                write!(f, "outline style {id:?} is not available")
+            }
+            OutlineError::UnsupportedTargetFormat(format) => {
+                write!(f, "can't render outlines to a target of format {format:?}")
+            }
+            OutlineError::InvalidStyleValue { field, value } => {
+                write!(
+                    f,
+                    "outline style `{field}` must be non-negative, got {value}"
+                )
+            }
+            OutlineError::TooManyObjects { objects, max } => {
+                write!(
+                    f,
+                    "{objects} objects are outlined, but only {max} have distinct IDs"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for OutlineError {}
+
+// Identifies a logged error, leaving out values which may change every frame,
+// so that a style animated through invalid values is only reported once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum WarningKey {
+    MissingStyle(HandleId),
+    UnsupportedTargetFormat(TextureFormat),
+    InvalidStyleValue(Option<HandleId>, &'static str),
+    TooManyObjects,
+}
+
+impl WarningKey {
+    fn new(error: &OutlineError, style: Option<HandleId>) -> WarningKey {
+        match error {
+            OutlineError::MissingStyle(id) => WarningKey::MissingStyle(*id),
+            OutlineError::UnsupportedTargetFormat(format) => {
+                WarningKey::UnsupportedTargetFormat(*format)
+            }
+            OutlineError::InvalidStyleValue { field, .. } => {
+                WarningKey::InvalidStyleValue(style, field)
+            }
+            OutlineError::TooManyObjects { .. } => WarningKey::TooManyObjects,
+        }
+    }
+}
+
+/// Errors which have already been logged by the render world.
+#[derive(Default, Resource)]
+pub struct OutlineWarnings(Mutex<HashSet<WarningKey>>);
+
+impl OutlineWarnings {
+    /// Logs `error` as a warning, unless it has been logged before.
+    pub(crate) fn warn_once(&self, error: &OutlineError, context: &str) {
+        if self.0.lock().unwrap().insert(WarningKey::new(error, None)) {
+            warn!("{error}; {context}");
+        }
+    }
+
+    /// Logs `error`, found in `style`, as a warning, unless it has been logged
+    /// for the style before.
+    pub(crate) fn warn_once_for_style(&self, style: HandleId, error: &OutlineError, context: &str) {
+        if self
+            .0
+            .lock()
+            .unwrap()
+            .insert(WarningKey::new(error, Some(style)))
+        {
+            warn!("{error} in outline style {style:?}; {context}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_values_are_keyed_by_style_and_field() {
+        let warnings = OutlineWarnings::default();
+        let [first, second] = [HandleId::random::<Image>(), HandleId::random::<Image>()];
+        let invalid = |field, value| OutlineError::InvalidStyleValue { field, value };
+
+        for value in [-1.0, -2.0, f32::NAN] {
+            warnings.warn_once_for_style(first, &invalid("width", value), "");
+        }
+        assert_eq!(warnings.0.lock().unwrap().len(), 1);
+
+        warnings.warn_once_for_style(first, &invalid("softness", -1.0), "");
+        warnings.warn_once_for_style(second, &invalid("width", -1.0), "");
+        assert_eq!(warnings.0.lock().unwrap().len(), 3);
+    }
+}
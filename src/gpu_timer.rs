@@ -0,0 +1,278 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_graph::{Node, NodeRunError, RenderGraphContext},
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+    },
+};
+use wgpu::{
+    Buffer, BufferDescriptor, BufferUsages, Device, Features, MapMode, QuerySet,
+    QuerySetDescriptor, QueryType,
+};
+
+use crate::timings::{OutlinePass, OutlinePassTiming, SharedGpuTimings};
+
+// Two timestamps per scope.
+const MAX_QUERIES: u32 = 256;
+const QUERY_SIZE: u64 = 8;
+const MAX_FRAMES_IN_FLIGHT: usize = 3;
+
+// States of a frame's read buffer, set once its mapping completes.
+const UNMAPPED: u8 = 0;
+const MAPPED: u8 = 1;
+const MAP_FAILED: u8 = 2;
+
+struct TimerFrame {
+    query_set: QuerySet,
+    // Queries are resolved directly into the mappable buffer.
+    read_buffer: Buffer,
+    // Scope `i` uses queries `2 * i` and `2 * i + 1`.
+    scopes: Vec<(Entity, OutlinePass)>,
+    resolved: bool,
+    map_state: Arc<AtomicU8>,
+}
+
+impl TimerFrame {
+    fn new(device: &Device) -> TimerFrame {
+        let query_set = device.create_query_set(&QuerySetDescriptor {
+            label: Some("outline_timer_query_set"),
+            ty: QueryType::Timestamp,
+            count: MAX_QUERIES,
+        });
+
+        let read_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("outline_timer_read_buffer"),
+            size: MAX_QUERIES as u64 * QUERY_SIZE,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        TimerFrame {
+            query_set,
+            read_buffer,
+            scopes: Vec::new(),
+            resolved: false,
+            map_state: Arc::new(AtomicU8::new(UNMAPPED)),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.scopes.clear();
+        self.resolved = false;
+        self.map_state.store(UNMAPPED, Ordering::Release);
+    }
+
+    /// Reads back the timings of a mapped frame.
+    fn read(&mut self, period: f32) -> Vec<OutlinePassTiming> {
+        let timings = {
+            let data = self.read_buffer.slice(..).get_mapped_range();
+            let ticks: Vec<u64> = data
+                .chunks_exact(QUERY_SIZE as usize)
+                .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+                .collect();
+
+            self.scopes
+                .iter()
+                .enumerate()
+                .map(|(i, &(view, pass))| {
+                    let elapsed = ticks[2 * i + 1].saturating_sub(ticks[2 * i]);
+                    OutlinePassTiming {
+                        view,
+                        pass,
+                        duration: Duration::from_nanos((elapsed as f64 * period as f64) as u64),
+                    }
+                })
+                .collect()
+        };
+
+        self.read_buffer.unmap();
+        self.reset();
+        timings
+    }
+}
+
+#[derive(Default)]
+struct TimerState {
+    free: Vec<TimerFrame>,
+    recording: Option<TimerFrame>,
+    // Frames waiting for their queries to be read back, oldest first.
+    pending: VecDeque<TimerFrame>,
+}
+
+/// Records GPU timestamps around the outline passes.
+#[derive(Resource)]
+pub(crate) struct GpuTimer {
+    // `None` if the device doesn't support timestamp queries.
+    state: Option<Mutex<TimerState>>,
+    // Nanoseconds per timestamp tick.
+    period: f32,
+}
+
+impl FromWorld for GpuTimer {
+    fn from_world(world: &mut World) -> Self {
+        let device = world.resource::<RenderDevice>();
+        let queue = world.resource::<RenderQueue>();
+
+        let state = device
+            .features()
+            .contains(Features::TIMESTAMP_QUERY)
+            .then(|| {
+                Mutex::new(TimerState {
+                    free: (0..MAX_FRAMES_IN_FLIGHT)
+                        .map(|_| TimerFrame::new(device.wgpu_device()))
+                        .collect(),
+                    ..default()
+                })
+            });
+
+        GpuTimer {
+            state,
+            period: queue.get_timestamp_period(),
+        }
+    }
+}
+
+impl GpuTimer {
+    /// Writes the starting timestamp of a scope, returning its first query.
+    pub(crate) fn begin(
+        &self,
+        render_context: &mut RenderContext,
+        view: Entity,
+        pass: OutlinePass,
+    ) -> Option<u32> {
+        let mut state = self.state.as_ref()?.lock().unwrap();
+        let frame = state.recording.as_mut().filter(|frame| !frame.resolved)?;
+
+        let query = 2 * frame.scopes.len() as u32;
+        if query + 2 > MAX_QUERIES {
+            return None;
+        }
+
+        frame.scopes.push((view, pass));
+        render_context
+            .command_encoder()
+            .write_timestamp(&frame.query_set, query);
+
+        Some(query)
+    }
+
+    /// Writes the ending timestamp of the scope started at `query`.
+    pub(crate) fn end(&self, render_context: &mut RenderContext, query: u32) {
+        let Some(state) = &self.state else {
+            return;
+        };
+
+        if let Some(frame) = &state.lock().unwrap().recording {
+            render_context
+                .command_encoder()
+                .write_timestamp(&frame.query_set, query + 1);
+        }
+    }
+}
+
+/// Publishes timings read back since the last frame, and starts recording.
+pub(crate) fn begin_gpu_timer_frame(timer: Res<GpuTimer>, shared: Res<SharedGpuTimings>) {
+    let Some(state) = &timer.state else {
+        return;
+    };
+    let state = &mut *state.lock().unwrap();
+
+    while let Some(frame) = state.pending.front() {
+        let map_state = frame.map_state.load(Ordering::Acquire);
+        if map_state == UNMAPPED {
+            break;
+        }
+
+        let mut frame = state.pending.pop_front().unwrap();
+        // Frames which failed to map are recycled without their timings.
+        if map_state == MAPPED {
+            *shared.0.lock().unwrap() = Some(frame.read(timer.period));
+        } else {
+            frame.reset();
+        }
+        state.free.push(frame);
+    }
+
+    // A frame left over from a frame that wasn't rendered is reused.
+    if state.recording.is_none() {
+        state.recording = state.free.pop();
+    }
+}
+
+/// Maps the queries recorded this frame, once they have been submitted.
+pub(crate) fn end_gpu_timer_frame(timer: Res<GpuTimer>) {
+    let Some(state) = &timer.state else {
+        return;
+    };
+    let state = &mut *state.lock().unwrap();
+
+    let Some(mut frame) = state.recording.take() else {
+        return;
+    };
+
+    if !frame.resolved {
+        frame.reset();
+        state.recording = Some(frame);
+        return;
+    }
+
+    let map_state = frame.map_state.clone();
+    frame
+        .read_buffer
+        .slice(..)
+        .map_async(MapMode::Read, move |result| {
+            let state = if result.is_ok() { MAPPED } else { MAP_FAILED };
+            map_state.store(state, Ordering::Release);
+        });
+    state.pending.push_back(frame);
+}
+
+/// Render graph node for resolving timestamp queries, after all views are
+/// rendered.
+pub struct GpuTimerNode;
+
+impl GpuTimerNode {
+    pub const NAME: &'static str = "outline_gpu_timer";
+}
+
+impl Node for GpuTimerNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let Some(state) = &world.resource::<GpuTimer>().state else {
+            return Ok(());
+        };
+
+        let mut state = state.lock().unwrap();
+        let Some(frame) = state
+            .recording
+            .as_mut()
+            .filter(|frame| !frame.scopes.is_empty())
+        else {
+            return Ok(());
+        };
+
+        let count = 2 * frame.scopes.len() as u32;
+        render_context.command_encoder().resolve_query_set(
+            &frame.query_set,
+            0..count,
+            &frame.read_buffer,
+            0,
+        );
+        frame.resolved = true;
+
+        Ok(())
+    }
+}
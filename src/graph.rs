@@ -1,19 +1,21 @@
 use bevy::{
+    core_pipeline::core_3d,
     prelude::*,
     render::{
         render_graph::{
             Node, NodeRunError, RenderGraph, RenderGraphContext, RenderGraphError, SlotInfo,
             SlotType,
         },
-        render_resource::TextureFormat,
         renderer::RenderContext,
-        texture::BevyDefault,
     },
 };
 
-use crate::{jfa::JfaNode, jfa_init::JfaInitNode, mask::MeshMaskNode, outline::OutlineNode};
+use crate::{
+    hull::HullNode, jfa::JfaNode, jfa_init::JfaInitNode, mask::MeshMaskNode, outline::OutlineNode,
+};
 
-pub(crate) mod outline {
+/// Labels of the outline subgraph, which is added to the `core_3d` graph.
+pub mod outline {
     pub const NAME: &str = "outline_graph";
 
     pub mod input {
@@ -25,9 +27,98 @@ pub(crate) mod outline {
         pub const JFA_INIT_PASS: &str = "jfa_init_pass";
         pub const JFA_PASS: &str = "jfa_pass";
         pub const OUTLINE_PASS: &str = "outline_pass";
+        pub const HULL_PASS: &str = "hull_pass";
+    }
+}
+
+/// A node of the `core_3d` graph to place the outline passes before or after.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OutlineAnchor {
+    Bloom,
+    Tonemapping,
+    Fxaa,
+    /// The UI pass, if `bevy_ui` is rendering.
+    Ui,
+}
+
+// The label of `bevy_ui::draw_ui_graph::node::UI_PASS`.
+const UI_PASS: &str = "ui_pass";
+
+// Nodes of the `core_3d` graph in the order they run.
+const CORE_3D_ORDER: [&str; 7] = [
+    core_3d::graph::node::MAIN_PASS,
+    core_3d::graph::node::BLOOM,
+    core_3d::graph::node::TONEMAPPING,
+    core_3d::graph::node::FXAA,
+    core_3d::graph::node::END_MAIN_PASS_POST_PROCESSING,
+    UI_PASS,
+    core_3d::graph::node::UPSCALING,
+];
+
+impl OutlineAnchor {
+    fn order(self) -> usize {
+        match self {
+            OutlineAnchor::Bloom => 1,
+            OutlineAnchor::Tonemapping => 2,
+            OutlineAnchor::Fxaa => 3,
+            OutlineAnchor::Ui => 5,
+        }
+    }
+}
+
+/// Where the [`OutlineDriverNode`] runs in the `core_3d` graph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OutlinePlacement {
+    Before(OutlineAnchor),
+    After(OutlineAnchor),
+    /// The outline subgraph is added to the `core_3d` graph, but the
+    /// [`OutlineDriverNode`] isn't. Add it and its edges to run the outline
+    /// passes elsewhere.
+    Manual,
+}
+
+impl Default for OutlinePlacement {
+    fn default() -> Self {
+        OutlinePlacement::Before(OutlineAnchor::Bloom)
+    }
+}
+
+impl OutlinePlacement {
+    /// Adds the [`OutlineDriverNode`] to `draw_3d_graph`, ordered by the
+    /// placement.
+    ///
+    /// Anchors missing from the graph are skipped over, so the driver still
+    /// runs between their neighbors.
+    pub fn add_driver(self, draw_3d_graph: &mut RenderGraph) {
+        let (before, after) = match self {
+            OutlinePlacement::Before(anchor) => (anchor.order() - 1, anchor.order()),
+            OutlinePlacement::After(anchor) => (anchor.order(), anchor.order() + 1),
+            OutlinePlacement::Manual => return,
+        };
+
+        let exists = |label: &&'static str| draw_3d_graph.get_node_state(*label).is_ok();
+        let before = CORE_3D_ORDER[..=before].iter().copied().rev().find(exists);
+        let after = CORE_3D_ORDER[after..].iter().copied().find(exists);
+
+        let input = draw_3d_graph.input_node().id;
+        draw_3d_graph.add_node(OutlineDriverNode::NAME, OutlineDriverNode);
+        draw_3d_graph.add_slot_edge(
+            input,
+            core_3d::graph::input::VIEW_ENTITY,
+            OutlineDriverNode::NAME,
+            OutlineDriverNode::INPUT_VIEW,
+        );
+
+        if let Some(before) = before {
+            draw_3d_graph.add_node_edge(before, OutlineDriverNode::NAME);
+        }
+        if let Some(after) = after {
+            draw_3d_graph.add_node_edge(OutlineDriverNode::NAME, after);
+        }
     }
 }
 
+/// Render graph node for running the outline subgraph on a view.
 pub struct OutlineDriverNode;
 
 impl OutlineDriverNode {
@@ -58,7 +149,10 @@ impl Node for OutlineDriverNode {
 }
 
 /// Builds the render graph for applying the JFA outline.
-pub fn outline(render_app: &mut App) -> Result<RenderGraph, RenderGraphError> {
+///
+/// The render resources of the [`OutlinePlugin`](crate::OutlinePlugin) must
+/// already be initialized.
+pub fn build_outline_graph(render_app: &mut App) -> Result<RenderGraph, RenderGraphError> {
     let mut graph = RenderGraph::default();
 
     let input_node_id = graph.set_input(vec![SlotInfo {
@@ -71,18 +165,18 @@ pub fn outline(render_app: &mut App) -> Result<RenderGraph, RenderGraphError> {
     // 2. JFA Init
     // 3. JFA
     // 4. Outline
+    // 5. Hull, for views outlined by hulls instead
 
     let mask_node = MeshMaskNode::new(&mut render_app.world);
     let jfa_node = JfaNode::from_world(&mut render_app.world);
-    // TODO: BevyDefault for surface texture format is an anti-pattern;
-    // the target texture format should be queried from the window when
-    // Bevy exposes that functionality.
-    let outline_node = OutlineNode::new(&mut render_app.world, TextureFormat::bevy_default());
+    let outline_node = OutlineNode::from_world(&mut render_app.world);
+    let hull_node = HullNode::from_world(&mut render_app.world);
 
     graph.add_node(outline::node::MASK_PASS, mask_node);
     graph.add_node(outline::node::JFA_INIT_PASS, JfaInitNode);
     graph.add_node(outline::node::JFA_PASS, jfa_node);
     graph.add_node(outline::node::OUTLINE_PASS, outline_node);
+    graph.add_node(outline::node::HULL_PASS, hull_node);
 
     // Input -> Mask
     graph.add_slot_edge(
@@ -92,6 +186,14 @@ pub fn outline(render_app: &mut App) -> Result<RenderGraph, RenderGraphError> {
         MeshMaskNode::IN_VIEW,
     );
 
+    // Input -> JFA Init
+    graph.add_slot_edge(
+        input_node_id,
+        outline::input::VIEW_ENTITY,
+        outline::node::JFA_INIT_PASS,
+        JfaInitNode::IN_VIEW,
+    );
+
     // Mask -> JFA Init
     graph.add_slot_edge(
         outline::node::MASK_PASS,
@@ -132,5 +234,16 @@ pub fn outline(render_app: &mut App) -> Result<RenderGraph, RenderGraphError> {
         OutlineNode::IN_JFA,
     );
 
+    // Input -> Hull
+    graph.add_slot_edge(
+        input_node_id,
+        outline::input::VIEW_ENTITY,
+        outline::node::HULL_PASS,
+        HullNode::IN_VIEW,
+    );
+
+    // Outline -> Hull
+    graph.add_node_edge(outline::node::OUTLINE_PASS, outline::node::HULL_PASS);
+
     Ok(graph)
 }
@@ -0,0 +1,217 @@
+use bevy::{
+    ecs::{
+        entity::{EntityMap, MapEntities, MapEntitiesError},
+        reflect::{ReflectComponent, ReflectMapEntities},
+    },
+    prelude::*,
+    utils::HashMap,
+};
+
+use crate::Outline;
+
+/// Component controlling whether an entity's [`Outline`] applies to its
+/// descendants.
+///
+/// This is useful for outlining entities whose meshes live on child entities,
+/// such as spawned glTF scenes or multi-mesh characters. Descendants spawned
+/// after the component is added, e.g. by the scene spawner, are picked up
+/// automatically.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Component, Reflect, FromReflect)]
+#[reflect(Component, Default)]
+pub enum OutlinePropagation {
+    /// The outline applies only to the entity itself.
+    #[default]
+    Entity,
+    /// The outline applies to the entity and all of its descendants.
+    Descendants,
+}
+
+/// Outline state inherited from an ancestor with
+/// [`OutlinePropagation::Descendants`].
+///
+/// This component is managed by the [`OutlinePlugin`](crate::OutlinePlugin)
+/// and should not be inserted manually. An [`Outline`] on the entity itself
+/// takes precedence over the inherited state.
+#[derive(Clone, Debug, PartialEq, Component, Reflect, FromReflect)]
+#[reflect(Component, MapEntities)]
+pub struct InheritedOutline {
+    pub(crate) source: Entity,
+    pub(crate) enabled: bool,
+}
+
+impl InheritedOutline {
+    /// Returns the ancestor this outline is inherited from.
+    pub fn source(&self) -> Entity {
+        self.source
+    }
+
+    /// Returns whether the inherited outline is enabled.
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+// Required for reflection. The placeholder source is replaced as soon as
+// outlines are propagated.
+impl FromWorld for InheritedOutline {
+    fn from_world(_world: &mut World) -> Self {
+        InheritedOutline {
+            source: Entity::PLACEHOLDER,
+            enabled: false,
+        }
+    }
+}
+
+impl MapEntities for InheritedOutline {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        // The source may live outside the scene, in which case it is left as is.
+        if let Ok(source) = entity_map.get(self.source) {
+            self.source = source;
+        }
+        Ok(())
+    }
+}
+
+type ChangedOutlineFilter = Or<(
+    Changed<Outline>,
+    Changed<OutlinePropagation>,
+    Changed<Children>,
+)>;
+
+/// Returns whether an outline, its propagation or the hierarchy changed since
+/// outlines were last propagated.
+pub(crate) fn outlines_changed(
+    changed: Query<(), ChangedOutlineFilter>,
+    mut removed_outlines: RemovedComponents<Outline>,
+    mut removed_propagations: RemovedComponents<OutlinePropagation>,
+    mut removed_children: RemovedComponents<Children>,
+) -> bool {
+    // Every removal is read, so none is seen again next frame.
+    let removed = removed_outlines.iter().count()
+        + removed_propagations.iter().count()
+        + removed_children.iter().count();
+    removed > 0 || !changed.is_empty()
+}
+
+/// Applies outlines with [`OutlinePropagation::Descendants`] to their
+/// descendants.
+pub(crate) fn propagate_outlines(
+    mut commands: Commands,
+    roots: Query<(Entity, &Outline, &OutlinePropagation, &Children)>,
+    propagating: Query<&OutlinePropagation, With<Outline>>,
+    children_query: Query<&Children>,
+    mut inherited_query: Query<(Entity, &mut InheritedOutline)>,
+    mut computed: Local<HashMap<Entity, InheritedOutline>>,
+    mut stack: Local<Vec<Entity>>,
+) {
+    for (source, outline, propagation, children) in roots.iter() {
+        if *propagation != OutlinePropagation::Descendants {
+            continue;
+        }
+
+        stack.extend(children.iter().copied());
+        while let Some(entity) = stack.pop() {
+            // Nested propagating outlines take over their own subtree.
+            if matches!(propagating.get(entity), Ok(OutlinePropagation::Descendants)) {
+                continue;
+            }
+
+            computed.insert(
+                entity,
+                InheritedOutline {
+                    source,
+                    enabled: outline.enabled,
+                },
+            );
+
+            if let Ok(children) = children_query.get(entity) {
+                stack.extend(children.iter().copied());
+            }
+        }
+    }
+
+    for (entity, mut inherited) in inherited_query.iter_mut() {
+        match computed.remove(&entity) {
+            Some(new) => {
+                if *inherited != new {
+                    *inherited = new;
+                }
+            }
+            None => {
+                commands.entity(entity).remove::<InheritedOutline>();
+            }
+        }
+    }
+
+    for (entity, new) in computed.drain() {
+        if let Some(mut entity_commands) = commands.get_entity(entity) {
+            entity_commands.insert(new);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_system(propagate_outlines.run_if(outlines_changed));
+        app
+    }
+
+    fn source(app: &App, entity: Entity) -> Option<Entity> {
+        app.world.get::<InheritedOutline>(entity).map(|i| i.source)
+    }
+
+    #[test]
+    fn nested_roots_take_over_their_subtree() {
+        let mut app = app();
+        let leaf = app.world.spawn_empty().id();
+        let nested = app
+            .world
+            .spawn((Outline::default(), OutlinePropagation::Descendants))
+            .push_children(&[leaf])
+            .id();
+        let sibling = app.world.spawn_empty().id();
+        let root = app
+            .world
+            .spawn((Outline { enabled: false }, OutlinePropagation::Descendants))
+            .push_children(&[nested, sibling])
+            .id();
+        app.update();
+
+        assert_eq!(source(&app, sibling), Some(root));
+        assert_eq!(source(&app, nested), None);
+        assert_eq!(source(&app, leaf), Some(nested));
+
+        app.world.get_mut::<Outline>(root).unwrap().enabled = true;
+        app.update();
+        assert!(app.world.get::<InheritedOutline>(sibling).unwrap().enabled);
+    }
+
+    #[test]
+    fn reparented_and_removed_outlines_are_updated() {
+        let mut app = app();
+        let child = app.world.spawn_empty().id();
+        let root = app
+            .world
+            .spawn((Outline::default(), OutlinePropagation::Descendants))
+            .push_children(&[child])
+            .id();
+        let other = app
+            .world
+            .spawn((Outline::default(), OutlinePropagation::Descendants))
+            .id();
+        app.update();
+        assert_eq!(source(&app, child), Some(root));
+
+        app.world.entity_mut(other).push_children(&[child]);
+        app.update();
+        assert_eq!(source(&app, child), Some(other));
+
+        app.world.entity_mut(other).remove::<OutlinePropagation>();
+        app.update();
+        assert_eq!(source(&app, child), None);
+    }
+}
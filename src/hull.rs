@@ -0,0 +1,428 @@
+use bevy::{
+    pbr::{MeshPipeline, MeshPipelineKey, MeshUniform, SetMeshBindGroup, SetMeshViewBindGroup},
+    prelude::*,
+    render::{
+        camera::ExtractedCamera,
+        mesh::MeshVertexBufferLayout,
+        render_asset::RenderAssets,
+        render_graph::{Node, NodeRunError, RenderGraphContext, SlotInfo, SlotType},
+        render_phase::{
+            CachedRenderPipelinePhaseItem, DrawFunctionId, DrawFunctions, PhaseItem, RenderPhase,
+            SetItemPipeline,
+        },
+        render_resource::{
+            BindGroupLayout, CachedRenderPipelineId, ColorTargetState, ColorWrites, Extent3d, Face,
+            FragmentState, LoadOp, MultisampleState, Operations, PipelineCache,
+            RenderPassColorAttachment, RenderPassDepthStencilAttachment, RenderPassDescriptor,
+            RenderPipelineDescriptor, SpecializedMeshPipeline, SpecializedMeshPipelineError,
+            SpecializedMeshPipelines, TextureDescriptor, TextureFormat, TextureUsages,
+        },
+        renderer::{RenderContext, RenderDevice},
+        texture::{BevyDefault, CachedTexture, TextureCache},
+        view::{ExtractedView, ViewTarget, VisibleEntities},
+    },
+    utils::FloatOrd,
+};
+
+use crate::{
+    mask::{DrawOutlineMesh, MeshMaskPipeline, SetOutlineMeshBindGroup},
+    outline::{SetOutlineParamsBindGroup, ViewOutlineParams, OUTLINE_BLEND},
+    overscan::OverscanVisibleEntities,
+    resources::{tex_desc, OutlineResources},
+    timings::{time_pass, OutlinePass},
+    CameraOutline, ExtractedOutline, OutlineMethod, OutlineSettings, HULL_SHADER_HANDLE,
+};
+
+/// A mesh drawn by the hull outline pass.
+pub(crate) struct HullOutline {
+    distance: f32,
+    pipeline: CachedRenderPipelineId,
+    entity: Entity,
+    draw_function: DrawFunctionId,
+}
+
+impl PhaseItem for HullOutline {
+    type SortKey = FloatOrd;
+
+    fn sort_key(&self) -> Self::SortKey {
+        FloatOrd(self.distance)
+    }
+
+    fn draw_function(&self) -> DrawFunctionId {
+        self.draw_function
+    }
+
+    fn entity(&self) -> Entity {
+        self.entity
+    }
+}
+
+impl CachedRenderPipelinePhaseItem for HullOutline {
+    fn cached_pipeline(&self) -> CachedRenderPipelineId {
+        self.pipeline
+    }
+}
+
+pub(crate) type DrawHullOutline = (
+    SetItemPipeline,
+    SetMeshViewBindGroup<0>,
+    SetMeshBindGroup<1>,
+    SetOutlineMeshBindGroup<2>,
+    SetOutlineParamsBindGroup<3>,
+    DrawOutlineMesh,
+);
+
+#[derive(Resource)]
+pub(crate) struct HullPipeline {
+    mesh_pipeline: MeshPipeline,
+    outline_mesh_layout: BindGroupLayout,
+    params_layout: BindGroupLayout,
+}
+
+impl FromWorld for HullPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let mesh_pipeline = world.resource::<MeshPipeline>().clone();
+        let outline_mesh_layout = world
+            .resource::<MeshMaskPipeline>()
+            .outline_mesh_layout
+            .clone();
+        let params_layout = world
+            .resource::<OutlineResources>()
+            .outline_params_bind_group_layout
+            .clone();
+
+        HullPipeline {
+            mesh_pipeline,
+            outline_mesh_layout,
+            params_layout,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct HullPipelineKey {
+    mesh: MeshPipelineKey,
+    format: TextureFormat,
+    // Whether the extruded hull is drawn, rather than the depth of the mesh
+    // hiding it.
+    hull: bool,
+}
+
+impl SpecializedMeshPipeline for HullPipeline {
+    type Key = HullPipelineKey;
+
+    fn specialize(
+        &self,
+        key: Self::Key,
+        layout: &MeshVertexBufferLayout,
+    ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
+        let mut desc = self.mesh_pipeline.specialize(key.mesh, layout)?;
+
+        desc.layout = vec![
+            self.mesh_pipeline.view_layout_multisampled.clone(),
+            self.mesh_pipeline.mesh_layout.clone(),
+            self.outline_mesh_layout.clone(),
+            self.params_layout.clone(),
+        ];
+
+        let mut shader_defs = vec![];
+        if key.hull {
+            shader_defs.push("HULL".into());
+            // Only the back faces of the hull show around the mesh.
+            desc.primitive.cull_mode = Some(Face::Front);
+        }
+
+        desc.vertex.shader = HULL_SHADER_HANDLE.typed::<Shader>();
+        desc.vertex.shader_defs.extend(shader_defs.iter().cloned());
+        desc.fragment = Some(FragmentState {
+            shader: HULL_SHADER_HANDLE.typed::<Shader>(),
+            shader_defs,
+            entry_point: "fragment".into(),
+            targets: vec![Some(ColorTargetState {
+                format: key.format,
+                blend: Some(OUTLINE_BLEND),
+                write_mask: if key.hull {
+                    ColorWrites::ALL
+                } else {
+                    ColorWrites::empty()
+                },
+            })],
+        });
+        // The depth of the meshes is kept from the mesh pipeline, so hulls are
+        // drawn only outside of them. Hulls write their depth too, so that
+        // translucent outlines aren't drawn twice where hulls overlap.
+
+        desc.multisample = MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        };
+
+        desc.label = Some("outline_hull_pipeline".into());
+        Ok(desc)
+    }
+}
+
+// Returns whether meshes with `layout` can be drawn by the hull pipeline. Hulls
+// are extruded along the normals, and the pipeline doesn't skin meshes.
+fn can_draw_hull(layout: &MeshVertexBufferLayout) -> bool {
+    layout.contains(Mesh::ATTRIBUTE_NORMAL) && !layout.contains(Mesh::ATTRIBUTE_JOINT_INDEX)
+}
+
+/// Depth of the outlined meshes, hiding the hulls behind them.
+#[derive(Component)]
+pub(crate) struct ViewHullDepth(pub(crate) CachedTexture);
+
+pub(crate) fn prepare_hull_depth(
+    mut commands: Commands,
+    device: Res<RenderDevice>,
+    mut textures: ResMut<TextureCache>,
+    views: Query<(
+        Entity,
+        &ExtractedView,
+        &OutlineSettings,
+        Option<&ExtractedCamera>,
+    )>,
+) {
+    for (entity, view, settings, camera) in views.iter() {
+        if settings.method != OutlineMethod::Hull {
+            continue;
+        }
+
+        // Matches the view target, which the pass draws to.
+        let target_size = camera
+            .and_then(|camera| camera.physical_target_size)
+            .unwrap_or(UVec2::new(view.viewport.z, view.viewport.w));
+        let size = Extent3d {
+            width: target_size.x.max(1),
+            height: target_size.y.max(1),
+            depth_or_array_layers: 1,
+        };
+        let depth = textures.get(
+            &device,
+            TextureDescriptor {
+                usage: TextureUsages::RENDER_ATTACHMENT,
+                ..tex_desc("outline_hull_depth", size, TextureFormat::Depth32Float)
+            },
+        );
+        commands.entity(entity).insert(ViewHullDepth(depth));
+    }
+}
+
+type HullViewQuery = (
+    &'static ExtractedView,
+    &'static VisibleEntities,
+    &'static mut RenderPhase<HullOutline>,
+    Option<&'static CameraOutline>,
+    Option<&'static OutlineSettings>,
+    Option<&'static ViewOutlineParams>,
+    Option<&'static OverscanVisibleEntities>,
+);
+
+pub(crate) fn queue_hull_outlines(
+    draw_functions: Res<DrawFunctions<HullOutline>>,
+    hull_pipeline: Res<HullPipeline>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<HullPipeline>>,
+    pipeline_cache: Res<PipelineCache>,
+    render_meshes: Res<RenderAssets<Mesh>>,
+    outline_meshes: Query<(Entity, &MeshUniform, &ExtractedOutline)>,
+    mut views: Query<HullViewQuery>,
+) {
+    let draw_hull = draw_functions.read().get_id::<DrawHullOutline>().unwrap();
+
+    for (view, visible_entities, mut hull_phase, camera_outline, settings, outline, overscan) in
+        views.iter_mut()
+    {
+        // Views without outline parameters have a missing style.
+        let (Some(settings), Some(_)) = (settings, outline) else {
+            continue;
+        };
+        if settings.method != OutlineMethod::Hull {
+            continue;
+        }
+        let outline_layers = camera_outline.map(|outline| outline.layers);
+        let format = if view.hdr {
+            ViewTarget::TEXTURE_FORMAT_HDR
+        } else {
+            TextureFormat::bevy_default()
+        };
+
+        let view_matrix = view.transform.compute_matrix();
+        let inv_view_row_2 = view_matrix.inverse().row(2);
+
+        // The depth of every mesh is drawn before any hull.
+        let mut hulls = vec![];
+        let overscan = overscan.map(|overscan| &overscan.entities[..]);
+        for visible_entity in visible_entities
+            .entities
+            .iter()
+            .chain(overscan.unwrap_or_default())
+            .copied()
+        {
+            let Ok((entity, mesh_uniform, outline)) = outline_meshes.get(visible_entity) else {
+                continue;
+            };
+
+            if let Some(layers) = outline_layers {
+                if !layers.intersects(&outline.layers) {
+                    continue;
+                }
+            }
+
+            let Some(mesh) = render_meshes.get(&outline.mesh) else {
+                continue;
+            };
+            if !can_draw_hull(&mesh.layout) {
+                continue;
+            }
+
+            let distance = inv_view_row_2.dot(mesh_uniform.transform.col(3));
+            for hull in [false, true] {
+                let key = HullPipelineKey {
+                    mesh: MeshPipelineKey::from_primitive_topology(mesh.primitive_topology),
+                    format,
+                    hull,
+                };
+                let pipeline = pipelines
+                    .specialize(&pipeline_cache, &hull_pipeline, key, &mesh.layout)
+                    .unwrap();
+
+                let item = HullOutline {
+                    distance,
+                    pipeline,
+                    entity,
+                    draw_function: draw_hull,
+                };
+                if hull {
+                    hulls.push(item);
+                } else {
+                    hull_phase.add(item);
+                }
+            }
+        }
+
+        for item in hulls {
+            hull_phase.add(item);
+        }
+    }
+}
+
+type HullNodeViewQuery = (
+    &'static RenderPhase<HullOutline>,
+    &'static ViewTarget,
+    &'static ViewHullDepth,
+    Option<&'static ExtractedCamera>,
+);
+
+/// Render graph node for drawing hull outlines onto the view.
+pub struct HullNode {
+    query: QueryState<HullNodeViewQuery>,
+}
+
+impl HullNode {
+    pub const IN_VIEW: &'static str = "view";
+}
+
+impl FromWorld for HullNode {
+    fn from_world(world: &mut World) -> Self {
+        HullNode {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for HullNode {
+    fn input(&self) -> Vec<SlotInfo> {
+        vec![SlotInfo::new(Self::IN_VIEW, SlotType::Entity)]
+    }
+
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
+        let Ok((hull_phase, target, depth, camera)) = self.query.get_manual(world, view_entity)
+        else {
+            return Ok(());
+        };
+        if hull_phase.items.is_empty() {
+            return Ok(());
+        }
+
+        time_pass(
+            world,
+            render_context,
+            view_entity,
+            OutlinePass::Hull,
+            |render_context| {
+                let mut tracked_pass =
+                    render_context.begin_tracked_render_pass(RenderPassDescriptor {
+                        label: Some("outline_hull_pass"),
+                        color_attachments: &[Some(RenderPassColorAttachment {
+                            view: target.main_texture(),
+                            resolve_target: None,
+                            ops: Operations {
+                                load: LoadOp::Load,
+                                store: true,
+                            },
+                        })],
+                        // Reverse-Z, like the main passes. Like other outlines,
+                        // hulls aren't occluded by the rest of the scene.
+                        depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                            view: &depth.0.default_view,
+                            depth_ops: Some(Operations {
+                                load: LoadOp::Clear(0.0),
+                                store: false,
+                            }),
+                            stencil_ops: None,
+                        }),
+                    });
+
+                if let Some(viewport) = camera.and_then(|camera| camera.viewport.as_ref()) {
+                    tracked_pass.set_camera_viewport(viewport);
+                }
+
+                hull_phase.render(&mut tracked_pass, world, view_entity);
+            },
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::render::mesh::VertexAttributeValues;
+
+    use super::*;
+
+    #[test]
+    fn hulls_need_normals_and_no_skinning() {
+        let cube = Mesh::from(shape::Cube::default());
+        assert!(can_draw_hull(&cube.get_mesh_vertex_buffer_layout()));
+
+        let mut without_normals = cube.clone();
+        without_normals.remove_attribute(Mesh::ATTRIBUTE_NORMAL);
+        assert!(!can_draw_hull(
+            &without_normals.get_mesh_vertex_buffer_layout()
+        ));
+
+        let vertices = cube.count_vertices();
+        let mut skinned = cube;
+        skinned.insert_attribute(
+            Mesh::ATTRIBUTE_JOINT_INDEX,
+            VertexAttributeValues::Uint16x4(vec![[0; 4]; vertices]),
+        );
+        skinned.insert_attribute(
+            Mesh::ATTRIBUTE_JOINT_WEIGHT,
+            vec![[1.0, 0.0, 0.0, 0.0]; vertices],
+        );
+        assert!(!can_draw_hull(&skinned.get_mesh_vertex_buffer_layout()));
+    }
+}
@@ -0,0 +1,550 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::{
+    ecs::reflect::ReflectComponent,
+    prelude::*,
+    render::{
+        render_asset::RenderAssets,
+        render_graph::{Node, NodeRunError, RenderGraphContext},
+        render_resource::{
+            BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+            BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType,
+            BufferBindingType, CachedRenderPipelineId, ColorTargetState, ColorWrites, Extent3d,
+            FragmentState, LoadOp, MultisampleState, Operations, PipelineCache,
+            RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
+            RenderPipelineDescriptor, SamplerBindingType, ShaderStages, ShaderType,
+            SpecializedRenderPipeline, SpecializedRenderPipelines, Texture, TextureDimension,
+            TextureFormat, TextureSampleType, TextureUsages, TextureView, TextureViewDimension,
+            UniformBuffer, VertexState,
+        },
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        texture::{CachedTexture, TextureCache},
+        Extract,
+    },
+    utils::HashSet,
+};
+
+use crate::{
+    jfa::{self, JfaPipeline},
+    jfa_init::JfaInitPipeline,
+    outline,
+    resources::{self, OutlineResources},
+    FULLSCREEN_PRIMITIVE_STATE, JFA_DISTANCE_SHADER_HANDLE, JFA_TEXTURE_FORMAT,
+};
+
+/// Component requesting a distance field for an image, generated on the GPU.
+///
+/// The red channel of `source` is used as coverage, as with the outline mask,
+/// so it must be a single-sample 2D texture with a float format.
+/// Each texel of `target` receives the distance to the nearest covered texel
+/// of `source`, mapped from 1 at the edge to 0 at `spread` pixels. `target`
+/// must be created with [`TextureUsages::RENDER_ATTACHMENT`] and a color
+/// format that can be rendered to, and is usually the same size as `source`.
+///
+/// Once the passes have been submitted, the component is removed and a
+/// [`JfaImageRequestComplete`] event is sent.
+#[derive(Clone, Debug, Component, Reflect, FromReflect)]
+#[reflect(Component, Default)]
+pub struct JfaImageRequest {
+    pub source: Handle<Image>,
+    pub target: Handle<Image>,
+    /// Distance in pixels mapped to the full output range.
+    pub spread: f32,
+}
+
+impl Default for JfaImageRequest {
+    fn default() -> Self {
+        Self {
+            source: Handle::default(),
+            target: Handle::default(),
+            spread: 8.0,
+        }
+    }
+}
+
+/// Event sent once the passes for a [`JfaImageRequest`] have been submitted.
+#[derive(Clone, Debug)]
+pub struct JfaImageRequestComplete {
+    /// The entity the request was on.
+    pub entity: Entity,
+    pub target: Handle<Image>,
+}
+
+type CompletedImageRequestList = Vec<(Entity, Handle<Image>)>;
+
+/// Requests submitted by the render world, to be reported in the main world.
+#[derive(Clone, Default, Resource)]
+pub(crate) struct CompletedImageRequests(Arc<Mutex<CompletedImageRequestList>>);
+
+pub(crate) fn send_image_request_events(
+    mut commands: Commands,
+    completed: Res<CompletedImageRequests>,
+    mut events: EventWriter<JfaImageRequestComplete>,
+) {
+    for (entity, target) in completed.0.lock().unwrap().drain(..) {
+        if let Some(mut entity_commands) = commands.get_entity(entity) {
+            entity_commands.remove::<JfaImageRequest>();
+        }
+
+        events.send(JfaImageRequestComplete { entity, target });
+    }
+}
+
+#[derive(Clone, Debug, ShaderType)]
+struct DistanceParams {
+    spread: f32,
+}
+
+/// Pipeline for converting the jump flood result into distances.
+#[derive(Resource)]
+pub struct JfaDistancePipeline {
+    dimensions_layout: BindGroupLayout,
+    input_layout: BindGroupLayout,
+}
+
+impl FromWorld for JfaDistancePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let res = world.resource::<OutlineResources>();
+        let dimensions_layout = res.dimensions_bind_group_layout.clone();
+
+        let device = world.resource::<RenderDevice>();
+        let input_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("jfa_distance_bind_group_layout"),
+            entries: &[
+                // JFA texture
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                // Sampler
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::NonFiltering),
+                    count: None,
+                },
+                // DistanceParams
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: Some(DistanceParams::min_size()),
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        JfaDistancePipeline {
+            dimensions_layout,
+            input_layout,
+        }
+    }
+}
+
+impl SpecializedRenderPipeline for JfaDistancePipeline {
+    type Key = TextureFormat;
+
+    fn specialize(&self, format: Self::Key) -> RenderPipelineDescriptor {
+        RenderPipelineDescriptor {
+            label: Some("jfa_distance_pipeline".into()),
+            layout: vec![self.dimensions_layout.clone(), self.input_layout.clone()],
+            vertex: VertexState {
+                shader: JFA_DISTANCE_SHADER_HANDLE.typed::<Shader>(),
+                shader_defs: vec![],
+                entry_point: "vertex".into(),
+                buffers: vec![],
+            },
+            fragment: Some(FragmentState {
+                shader: JFA_DISTANCE_SHADER_HANDLE.typed::<Shader>(),
+                shader_defs: vec![],
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: FULLSCREEN_PRIMITIVE_STATE,
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            push_constant_ranges: vec![],
+        }
+    }
+}
+
+/// Render world state for image requests.
+#[derive(Default, Resource)]
+pub(crate) struct ImageRequests {
+    // Requests waiting for their images or pipelines.
+    pending: Vec<(Entity, JfaImageRequest)>,
+    // Entities whose requests were submitted or rejected. Submitted requests
+    // stay on their entity until the main world has seen them complete.
+    done: HashSet<Entity>,
+    // Requests to run this frame.
+    prepared: Vec<PreparedImageRequest>,
+}
+
+struct PreparedImageRequest {
+    entity: Entity,
+    target: Handle<Image>,
+    target_view: TextureView,
+    distance_pipeline: CachedRenderPipelineId,
+    max_exp: usize,
+    primary: CachedTexture,
+    secondary: CachedTexture,
+    dimensions_bind_group: BindGroup,
+    init_bind_group: BindGroup,
+    from_primary_bind_group: BindGroup,
+    from_secondary_bind_group: BindGroup,
+    distance_bind_group: BindGroup,
+}
+
+pub(crate) fn extract_image_requests(
+    mut image_requests: ResMut<ImageRequests>,
+    requests: Extract<Query<(Entity, Ref<JfaImageRequest>)>>,
+) {
+    let ImageRequests { pending, done, .. } = &mut *image_requests;
+
+    // A changed request is run again.
+    done.retain(|entity| {
+        requests
+            .get(*entity)
+            .is_ok_and(|(_, request)| !request.is_changed())
+    });
+
+    pending.clear();
+    pending.extend(
+        requests
+            .iter()
+            .filter(|(entity, _)| !done.contains(entity))
+            .map(|(entity, request)| (entity, request.clone())),
+    );
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn prepare_image_requests(
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+    res: Res<OutlineResources>,
+    images: Res<RenderAssets<Image>>,
+    mut textures: ResMut<TextureCache>,
+    pipeline_cache: Res<PipelineCache>,
+    init_pipeline: Res<JfaInitPipeline>,
+    jfa_pipeline: Res<JfaPipeline>,
+    distance_pipeline: Res<JfaDistancePipeline>,
+    mut distance_pipelines: ResMut<SpecializedRenderPipelines<JfaDistancePipeline>>,
+    mut image_requests: ResMut<ImageRequests>,
+) {
+    let ImageRequests {
+        pending,
+        done,
+        prepared,
+    } = &mut *image_requests;
+
+    prepared.clear();
+    for (entity, request) in pending.iter() {
+        let (Some(source), Some(target)) =
+            (images.get(&request.source), images.get(&request.target))
+        else {
+            // Still loading.
+            continue;
+        };
+
+        if request.spread.is_nan() || request.spread <= 0.0 {
+            warn!("JfaImageRequest on {entity:?} has a non-positive spread");
+            done.insert(*entity);
+            continue;
+        }
+
+        if !is_sampleable_2d(&source.texture) {
+            warn!(
+                "JfaImageRequest source on {entity:?} can't be sampled; \
+                 it needs to be a single-sample 2D texture with a float format"
+            );
+            done.insert(*entity);
+            continue;
+        }
+
+        if !target
+            .texture
+            .usage()
+            .contains(TextureUsages::RENDER_ATTACHMENT)
+            || !outline::is_color_target_format(target.texture_format)
+        {
+            warn!(
+                "JfaImageRequest target on {entity:?} can't be rendered to; \
+                 it needs RENDER_ATTACHMENT usage and a color format"
+            );
+            done.insert(*entity);
+            continue;
+        }
+
+        let distance_pipeline_id = distance_pipelines.specialize(
+            &pipeline_cache,
+            &distance_pipeline,
+            target.texture_format,
+        );
+        // Requests are only run once all their pipelines are ready.
+        let ready = [
+            init_pipeline.standard.cached,
+            jfa_pipeline.cached,
+            distance_pipeline_id,
+        ]
+        .into_iter()
+        .all(|id| pipeline_cache.get_render_pipeline(id).is_some());
+        if !ready {
+            // Still queued.
+            continue;
+        }
+
+        let size = Extent3d {
+            width: source.texture.width(),
+            height: source.texture.height(),
+            depth_or_array_layers: 1,
+        };
+
+        let mut dimensions_buffer =
+            UniformBuffer::from(jfa::Dimensions::new(size.width, size.height));
+        dimensions_buffer.write_buffer(&device, &queue);
+        let dimensions_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("jfa_image_dimensions_bind_group"),
+            layout: &res.dimensions_bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: dimensions_buffer.binding().unwrap(),
+            }],
+        });
+
+        // The object ID texture is only read when separating objects.
+        let init_bind_group = resources::create_jfa_init_bind_group(
+            &device,
+            &res.jfa_init_bind_group_layout,
+            &source.texture_view,
+            &res.placeholder.default_view,
+            &res.sampler,
+        );
+
+        let primary = textures.get(
+            &device,
+            resources::tex_desc("jfa_image_primary_output", size, JFA_TEXTURE_FORMAT),
+        );
+        let secondary = textures.get(
+            &device,
+            resources::tex_desc("jfa_image_secondary_output", size, JFA_TEXTURE_FORMAT),
+        );
+
+        let [from_primary_bind_group, from_secondary_bind_group] = [
+            ("jfa_image_from_primary_bind_group", &primary),
+            ("jfa_image_from_secondary_bind_group", &secondary),
+        ]
+        .map(|(label, input)| {
+            resources::create_jfa_bind_group(
+                &device,
+                &res.jfa_bind_group_layout,
+                label,
+                res.jfa_distance_buffer.binding().unwrap(),
+                &input.default_view,
+                &res.sampler,
+            )
+        });
+
+        let max_exp = jfa::max_jump_exponent(request.spread, size.width as f32, size.height as f32);
+        // The last jump flood pass writes to the primary output after an odd
+        // number of passes.
+        let final_output = if max_exp % 2 == 1 {
+            &primary
+        } else {
+            &secondary
+        };
+
+        let mut params_buffer = UniformBuffer::from(DistanceParams {
+            spread: request.spread,
+        });
+        params_buffer.write_buffer(&device, &queue);
+        let distance_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("jfa_distance_bind_group"),
+            layout: &distance_pipeline.input_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&final_output.default_view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&res.sampler),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: params_buffer.binding().unwrap(),
+                },
+            ],
+        });
+
+        prepared.push(PreparedImageRequest {
+            entity: *entity,
+            target: request.target.clone_weak(),
+            target_view: target.texture_view.clone(),
+            distance_pipeline: distance_pipeline_id,
+            max_exp,
+            primary,
+            secondary,
+            dimensions_bind_group,
+            init_bind_group,
+            from_primary_bind_group,
+            from_secondary_bind_group,
+            distance_bind_group,
+        });
+    }
+}
+
+/// Returns whether `texture` can be bound as the coverage of a request.
+fn is_sampleable_2d(texture: &Texture) -> bool {
+    let sample_type = texture.format().describe().sample_type;
+
+    texture.dimension() == TextureDimension::D2
+        && texture.depth_or_array_layers() == 1
+        && texture.sample_count() == 1
+        && texture.usage().contains(TextureUsages::TEXTURE_BINDING)
+        && matches!(sample_type, TextureSampleType::Float { .. })
+}
+
+/// Reports requests run this frame to the main world.
+pub(crate) fn finish_image_requests(
+    mut image_requests: ResMut<ImageRequests>,
+    completed: Res<CompletedImageRequests>,
+) {
+    let ImageRequests { done, prepared, .. } = &mut *image_requests;
+
+    let mut completed = completed.0.lock().unwrap();
+    for request in prepared.drain(..) {
+        done.insert(request.entity);
+        completed.push((request.entity, request.target));
+    }
+}
+
+/// Render graph node for running image requests, ahead of the cameras.
+pub struct JfaImageNode;
+
+impl JfaImageNode {
+    pub const NAME: &'static str = "jfa_image";
+}
+
+impl Node for JfaImageNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let image_requests = world.resource::<ImageRequests>();
+        if image_requests.prepared.is_empty() {
+            return Ok(());
+        }
+
+        let res = world.resource::<OutlineResources>();
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let (Some(init_pipeline), Some(jfa_pipeline)) = (
+            pipeline_cache.get_render_pipeline(world.resource::<JfaInitPipeline>().standard.cached),
+            pipeline_cache.get_render_pipeline(world.resource::<JfaPipeline>().cached),
+        ) else {
+            // Requests are only prepared once their pipelines are ready.
+            return Ok(());
+        };
+
+        for request in image_requests.prepared.iter() {
+            let Some(distance_pipeline) =
+                pipeline_cache.get_render_pipeline(request.distance_pipeline)
+            else {
+                continue;
+            };
+
+            draw_fullscreen(
+                render_context,
+                "jfa_image_init",
+                &request.primary.default_view,
+                init_pipeline,
+                &[
+                    (&request.dimensions_bind_group, &[]),
+                    (&request.init_bind_group, &[]),
+                ],
+            );
+
+            for it in 0..=request.max_exp {
+                let exp = request.max_exp - it;
+
+                let (target, src) = if it % 2 == 1 {
+                    (&request.primary, &request.from_secondary_bind_group)
+                } else {
+                    (&request.secondary, &request.from_primary_bind_group)
+                };
+
+                draw_fullscreen(
+                    render_context,
+                    "jfa_image",
+                    &target.default_view,
+                    jfa_pipeline,
+                    &[
+                        (&request.dimensions_bind_group, &[]),
+                        (src, &[res.jfa_distance_offsets[exp]]),
+                    ],
+                );
+            }
+
+            draw_fullscreen(
+                render_context,
+                "jfa_image_distance",
+                &request.target_view,
+                distance_pipeline,
+                &[
+                    (&request.dimensions_bind_group, &[]),
+                    (&request.distance_bind_group, &[]),
+                ],
+            );
+        }
+
+        Ok(())
+    }
+}
+
+fn draw_fullscreen(
+    render_context: &mut RenderContext,
+    label: &str,
+    view: &TextureView,
+    pipeline: &RenderPipeline,
+    bind_groups: &[(&BindGroup, &[u32])],
+) {
+    let mut tracked_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: Operations {
+                load: LoadOp::Clear(
+                    Color::RgbaLinear {
+                        red: -1.0,
+                        green: -1.0,
+                        blue: 0.0,
+                        alpha: 0.0,
+                    }
+                    .into(),
+                ),
+                store: true,
+            },
+        })],
+        depth_stencil_attachment: None,
+    });
+    tracked_pass.set_render_pipeline(pipeline);
+    for (index, (bind_group, offsets)) in bind_groups.iter().enumerate() {
+        tracked_pass.set_bind_group(index, bind_group, offsets);
+    }
+    tracked_pass.draw(0..3, 0..1);
+}
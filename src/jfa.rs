@@ -1,20 +1,24 @@
 use bevy::{
     prelude::*,
     render::{
-        render_asset::RenderAssets,
         render_graph::{Node, NodeRunError, RenderGraphContext, SlotInfo, SlotType},
         render_resource::{
-            BindGroup, CachedRenderPipelineId, ColorTargetState, ColorWrites, FragmentState,
-            LoadOp, MultisampleState, Operations, PipelineCache, RenderPassColorAttachment,
-            RenderPassDescriptor, RenderPipelineDescriptor, ShaderType, TextureView, VertexState,
+            BindGroupLayout, CachedRenderPipelineId, ColorTargetState, ColorWrites, Extent3d,
+            FragmentState, LoadOp, MultisampleState, Operations, PipelineCache,
+            RenderPassColorAttachment, RenderPassDescriptor, RenderPipelineDescriptor, ShaderType,
+            TextureView, VertexState,
         },
         renderer::RenderContext,
     },
 };
 
 use crate::{
-    resources::OutlineResources, CameraOutline, OutlineStyle, FULLSCREEN_PRIMITIVE_STATE,
-    JFA_SHADER_HANDLE, JFA_TEXTURE_FORMAT,
+    outline::ViewOutlineParams,
+    resources::{OutlineResources, ViewOutlineTextures},
+    temporal::{self, ReuseOutlineFlood, ReuseOutlineHistory},
+    timings::{time_pass, OutlinePass},
+    OutlinePrecision, OutlineQuality, OutlineSettings, FULLSCREEN_PRIMITIVE_STATE,
+    JFA_SHADER_HANDLE, JFA_TEMPORAL_SHADER_HANDLE,
 };
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, ShaderType)]
@@ -28,6 +32,7 @@ pub struct Dimensions {
     height: f32,
     inv_width: f32,
     inv_height: f32,
+    overscan: f32,
 }
 
 impl Dimensions {
@@ -37,13 +42,96 @@ impl Dimensions {
             height: height as f32,
             inv_width: 1.0 / width as f32,
             inv_height: 1.0 / height as f32,
+            overscan: 0.0,
         }
     }
+
+    /// Sets the width in pixels of the guard band on each side of the view,
+    /// which is included in the dimensions.
+    pub fn with_overscan(mut self, overscan: u32) -> Dimensions {
+        self.overscan = overscan as f32;
+        self
+    }
+}
+
+/// Returns the exponent of the first jump distance needed to flood
+/// `flood_distance` pixels in a `width` by `height` texture.
+///
+/// Passes are run with jump distances `2^max_exp` down to `1`.
+pub fn max_jump_exponent(flood_distance: f32, width: f32, height: f32) -> usize {
+    // The half-width of the JFA region is 2^(max_exp + 1) - 1.
+    //
+    // weight < 2^(max_exp + 1) - 1
+    // weight + 1 < 2^(max_exp + 1)
+    // log2(weight + 1) < max_exp + 1
+    // max_exp > log2(weight + 1) - 1
+
+    let width = width.max(height).min(flood_distance.ceil());
+    width.log2() as usize
+}
+
+/// Returns the exponent of the first jump distance for a view with an outline.
+pub(crate) fn view_max_jump_exponent(
+    outline: &ViewOutlineParams,
+    settings: &OutlineSettings,
+    textures: &ViewOutlineTextures,
+) -> usize {
+    let dims = textures.dimensions;
+    max_jump_exponent(
+        outline.params.flood_distance(settings.separate_objects),
+        dims.width,
+        dims.height,
+    )
+}
+
+/// Returns the exponents of the jump distances of each pass for a view, in
+/// order.
+pub(crate) fn view_jump_exponents(
+    outline: &ViewOutlineParams,
+    settings: &OutlineSettings,
+    textures: &ViewOutlineTextures,
+    reuse_history: bool,
+) -> Vec<usize> {
+    let max_exp = view_max_jump_exponent(outline, settings, textures);
+    if reuse_history {
+        // Only short jumps are needed to correct the previous flood.
+        return (0..=max_exp.min(temporal::MAX_JUMP_EXPONENT))
+            .rev()
+            .collect();
+    }
+
+    let mut exps: Vec<_> = (0..=max_exp).rev().collect();
+    if settings.quality == OutlineQuality::High {
+        // JFA+1: a final one pixel jump.
+        exps.push(0);
+    }
+    exps
 }
 
 #[derive(Resource)]
 pub struct JfaPipeline {
-    cached: CachedRenderPipelineId,
+    pub(crate) cached: CachedRenderPipelineId,
+    // Variant writing `OutlinePrecision::High` textures.
+    high_precision: CachedRenderPipelineId,
+    // Passes merging the previous frame's flood into the seeds.
+    temporal: CachedRenderPipelineId,
+    temporal_high_precision: CachedRenderPipelineId,
+}
+
+impl JfaPipeline {
+    pub(crate) fn id(&self, precision: OutlinePrecision) -> CachedRenderPipelineId {
+        match precision {
+            OutlinePrecision::Standard => self.cached,
+            OutlinePrecision::High => self.high_precision,
+        }
+    }
+
+    fn temporal_id(&self, precision: OutlinePrecision) -> CachedRenderPipelineId {
+        match precision {
+            OutlinePrecision::Standard => self.temporal,
+            OutlinePrecision::High => self.temporal_high_precision,
+        }
+    }
 }
 
 impl FromWorld for JfaPipeline {
@@ -51,38 +139,74 @@ impl FromWorld for JfaPipeline {
         let res = world.get_resource::<OutlineResources>().unwrap();
         let dimensions_bind_group_layout = res.dimensions_bind_group_layout.clone();
         let jfa_bind_group_layout = res.jfa_bind_group_layout.clone();
+        let jfa_temporal_bind_group_layout = res.jfa_temporal_bind_group_layout.clone();
         let pipeline_cache = world.get_resource_mut::<PipelineCache>().unwrap();
-        let cached = pipeline_cache.queue_render_pipeline(RenderPipelineDescriptor {
-            label: Some("outline_jfa_pipeline".into()),
-            layout: vec![dimensions_bind_group_layout, jfa_bind_group_layout],
-            vertex: VertexState {
-                shader: JFA_SHADER_HANDLE.typed::<Shader>(),
-                shader_defs: vec![],
-                entry_point: "vertex".into(),
-                buffers: vec![],
-            },
-            fragment: Some(FragmentState {
-                shader: JFA_SHADER_HANDLE.typed::<Shader>(),
-                shader_defs: vec![],
-                entry_point: "fragment".into(),
-                targets: vec![Some(ColorTargetState {
-                    format: JFA_TEXTURE_FORMAT,
-                    blend: None,
-                    write_mask: ColorWrites::ALL,
-                })],
-            }),
-            primitive: FULLSCREEN_PRIMITIVE_STATE,
-            depth_stencil: None,
-            multisample: MultisampleState::default(),
-            push_constant_ranges: vec![],
-        });
-
-        JfaPipeline { cached }
+        let queue = |label: &'static str,
+                     shader: &HandleUntyped,
+                     layout: &BindGroupLayout,
+                     precision: OutlinePrecision| {
+            pipeline_cache.queue_render_pipeline(RenderPipelineDescriptor {
+                label: Some(label.into()),
+                layout: vec![dimensions_bind_group_layout.clone(), layout.clone()],
+                vertex: VertexState {
+                    shader: shader.typed_weak(),
+                    shader_defs: vec![],
+                    entry_point: "vertex".into(),
+                    buffers: vec![],
+                },
+                fragment: Some(FragmentState {
+                    shader: shader.typed_weak(),
+                    shader_defs: vec![],
+                    entry_point: "fragment".into(),
+                    targets: vec![Some(ColorTargetState {
+                        format: precision.jfa_texture_format(),
+                        blend: None,
+                        write_mask: ColorWrites::ALL,
+                    })],
+                }),
+                primitive: FULLSCREEN_PRIMITIVE_STATE,
+                depth_stencil: None,
+                multisample: MultisampleState::default(),
+                push_constant_ranges: vec![],
+            })
+        };
+
+        let [cached, high_precision] =
+            [OutlinePrecision::Standard, OutlinePrecision::High].map(|precision| {
+                queue(
+                    "outline_jfa_pipeline",
+                    &JFA_SHADER_HANDLE,
+                    &jfa_bind_group_layout,
+                    precision,
+                )
+            });
+        let [temporal, temporal_high_precision] =
+            [OutlinePrecision::Standard, OutlinePrecision::High].map(|precision| {
+                queue(
+                    "outline_jfa_temporal_pipeline",
+                    &JFA_TEMPORAL_SHADER_HANDLE,
+                    &jfa_temporal_bind_group_layout,
+                    precision,
+                )
+            });
+
+        JfaPipeline {
+            cached,
+            high_precision,
+            temporal,
+            temporal_high_precision,
+        }
     }
 }
 
+type JfaViewQuery = (
+    &'static ViewOutlineParams,
+    &'static OutlineSettings,
+    &'static ViewOutlineTextures,
+);
+
 pub struct JfaNode {
-    query: QueryState<&'static CameraOutline>,
+    query: QueryState<JfaViewQuery>,
 }
 
 impl FromWorld for JfaNode {
@@ -122,93 +246,127 @@ impl Node for JfaNode {
         world: &World,
     ) -> Result<(), NodeRunError> {
         let res = world.resource::<OutlineResources>();
-        graph
-            .set_output(Self::OUT_JUMP, res.jfa_final_output.default_view.clone())
-            .unwrap();
-
-        let styles = world.resource::<RenderAssets<OutlineStyle>>();
-        let width = match self
-            .query
-            .get_manual(world, graph.get_input_entity(Self::IN_VIEW)?)
-        {
-            Ok(outline) => {
-                let dims = res.dimensions_buffer.get();
-                dims.width
-                    .max(dims.height)
-                    .min(styles.get(&outline.style).unwrap().params.weight.ceil())
-            }
-            Err(_) => return Ok(()),
+        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
+        let Ok((outline, settings, textures)) = self.query.get_manual(world, view_entity) else {
+            graph.set_output(Self::OUT_JUMP, res.placeholder.default_view.clone())?;
+            return Ok(());
         };
+        graph.set_output(
+            Self::OUT_JUMP,
+            textures.jfa_final_output.default_view.clone(),
+        )?;
+        // Nothing changed since the previous flood.
+        if world.get::<ReuseOutlineFlood>(view_entity).is_some() {
+            return Ok(());
+        }
 
         let pipeline = world.get_resource::<JfaPipeline>().unwrap();
         let pipeline_cache = world.get_resource::<PipelineCache>().unwrap();
-        let cached_pipeline = match pipeline_cache.get_render_pipeline(pipeline.cached) {
-            Some(c) => c,
-            // Still queued.
-            None => {
-                return Ok(());
-            }
-        };
-
-        // The half-width of the JFA region is 2^(max_exp + 1) - 1.
-        //
-        // weight < 2^(max_exp + 1) - 1
-        // weight + 1 < 2^(max_exp + 1)
-        // log2(weight + 1) < max_exp + 1
-        // max_exp > log2(weight + 1) - 1
-
-        let max_exp = width.log2() as usize;
-        //let max_exp = width.log2().ceil() as usize;
-        for it in 0..=max_exp {
-            let exp = max_exp - it;
-
-            let target: &TextureView;
-            let src: &BindGroup;
-
-            if it % 2 == 1 {
-                if it == max_exp {
-                    target = &res.jfa_final_output.default_view;
-                } else {
-                    target = &res.jfa_primary_output.default_view;
+        let cached_pipeline =
+            match pipeline_cache.get_render_pipeline(pipeline.id(settings.precision)) {
+                Some(c) => c,
+                // Still queued.
+                None => {
+                    return Ok(());
                 }
-                src = &res.jfa_from_secondary_bind_group;
+            };
+
+        // The previous flood is merged into the seeds before the correction
+        // passes, once its pipeline is ready.
+        let temporal = textures
+            .jfa_temporal_bind_group
+            .as_ref()
+            .filter(|_| world.get::<ReuseOutlineHistory>(view_entity).is_some())
+            .zip(pipeline_cache.get_render_pipeline(pipeline.temporal_id(settings.precision)));
+        let exps = view_jump_exponents(outline, settings, textures, temporal.is_some());
+
+        // Passes alternate between the secondary and primary outputs, starting
+        // from the seeds in the primary output.
+        let first = temporal.is_some() as usize;
+        let last = first + exps.len() - 1;
+        let target = |it: usize| {
+            if it == last {
+                &textures.jfa_final_output.default_view
+            } else if it % 2 == 1 {
+                &textures.jfa_primary_output.default_view
             } else {
-                if it == max_exp {
-                    target = &res.jfa_final_output.default_view;
-                } else {
-                    target = &res.jfa_secondary_output.default_view;
-                }
-                src = &res.jfa_from_primary_bind_group;
+                &textures.jfa_secondary_output.default_view
             }
+        };
 
-            let attachment = RenderPassColorAttachment {
-                view: target,
-                resolve_target: None,
-                ops: Operations {
-                    // TODO: ideally, this would be the equivalent of DONT_CARE, but wgpu doesn't expose that.
-                    load: LoadOp::Clear(
-                        Color::RgbaLinear {
-                            red: -1.0,
-                            green: -1.0,
-                            blue: 0.0,
-                            alpha: 0.0,
-                        }
-                        .into(),
-                    ),
-                    store: true,
-                },
+        if let Some((temporal_bind_group, temporal_pipeline)) = temporal {
+            let attachment = jfa_attachment(target(0));
+            let pass = OutlinePass::JfaTemporal;
+            time_pass(world, render_context, view_entity, pass, |render_context| {
+                let mut tracked_pass =
+                    render_context.begin_tracked_render_pass(RenderPassDescriptor {
+                        label: Some("outline_jfa_temporal"),
+                        color_attachments: &[Some(attachment)],
+                        depth_stencil_attachment: None,
+                    });
+                tracked_pass.set_render_pipeline(temporal_pipeline);
+                tracked_pass.set_bind_group(0, &textures.dimensions_bind_group, &[]);
+                tracked_pass.set_bind_group(1, temporal_bind_group, &[]);
+                tracked_pass.draw(0..3, 0..1);
+            });
+        }
+
+        for (it, &exp) in (first..).zip(exps.iter()) {
+            let src = if it % 2 == 1 {
+                &textures.jfa_from_secondary_bind_group
+            } else {
+                &textures.jfa_from_primary_bind_group
             };
-            let mut tracked_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
-                label: Some("outline_jfa"),
-                color_attachments: &[Some(attachment)],
-                depth_stencil_attachment: None,
+
+            let attachment = jfa_attachment(target(it));
+            let pass = OutlinePass::Jfa(1 << exp);
+            time_pass(world, render_context, view_entity, pass, |render_context| {
+                let mut tracked_pass =
+                    render_context.begin_tracked_render_pass(RenderPassDescriptor {
+                        label: Some("outline_jfa"),
+                        color_attachments: &[Some(attachment)],
+                        depth_stencil_attachment: None,
+                    });
+                tracked_pass.set_render_pipeline(cached_pipeline);
+                tracked_pass.set_bind_group(0, &textures.dimensions_bind_group, &[]);
+                tracked_pass.set_bind_group(1, src, &[res.jfa_distance_offsets[exp]]);
+                tracked_pass.draw(0..3, 0..1);
             });
-            tracked_pass.set_render_pipeline(cached_pipeline);
-            tracked_pass.set_bind_group(0, &res.dimensions_bind_group, &[]);
-            tracked_pass.set_bind_group(1, src, &[res.jfa_distance_offsets[exp]]);
-            tracked_pass.draw(0..3, 0..1);
+        }
+
+        if let Some(history) = &textures.jfa_history {
+            let output = &textures.jfa_final_output.texture;
+            render_context.command_encoder().copy_texture_to_texture(
+                output.as_image_copy(),
+                history.texture.as_image_copy(),
+                Extent3d {
+                    width: output.width(),
+                    height: output.height(),
+                    depth_or_array_layers: 1,
+                },
+            );
         }
 
         Ok(())
     }
 }
+
+fn jfa_attachment(target: &TextureView) -> RenderPassColorAttachment<'_> {
+    RenderPassColorAttachment {
+        view: target,
+        resolve_target: None,
+        ops: Operations {
+            // TODO: ideally, this would be the equivalent of DONT_CARE, but wgpu doesn't expose that.
+            load: LoadOp::Clear(
+                Color::RgbaLinear {
+                    red: -1.0,
+                    green: -1.0,
+                    blue: 0.0,
+                    alpha: 0.0,
+                }
+                .into(),
+            ),
+            store: true,
+        },
+    }
+}
@@ -3,20 +3,67 @@ use bevy::{
     render::{
         render_graph::{Node, NodeRunError, RenderGraphContext, SlotInfo, SlotType},
         render_resource::{
-            CachedRenderPipelineId, ColorTargetState, ColorWrites, Face, FragmentState, FrontFace,
-            LoadOp, MultisampleState, Operations, PipelineCache, PolygonMode, PrimitiveState,
-            PrimitiveTopology, RenderPassColorAttachment, RenderPassDescriptor,
-            RenderPipelineDescriptor, VertexState,
+            BindGroupLayout, CachedRenderPipelineId, ColorTargetState, ColorWrites, Face,
+            FragmentState, FrontFace, LoadOp, MultisampleState, Operations, PipelineCache,
+            PolygonMode, PrimitiveState, PrimitiveTopology, RenderPassColorAttachment,
+            RenderPassDescriptor, RenderPipelineDescriptor, ShaderDefVal, TextureFormat,
+            VertexState,
         },
         renderer::RenderContext,
     },
 };
 
-use crate::{resources::OutlineResources, JFA_INIT_SHADER_HANDLE, JFA_TEXTURE_FORMAT};
+use crate::{
+    outline::ViewOutlineParams,
+    resources::{OutlineResources, ViewOutlineTextures},
+    temporal::ReuseOutlineFlood,
+    timings::{time_pass, OutlinePass},
+    OutlinePrecision, OutlineSettings, JFA_INIT_SHADER_HANDLE,
+};
 
 #[derive(Resource)]
 pub struct JfaInitPipeline {
-    cached: CachedRenderPipelineId,
+    pub(crate) standard: JfaInitVariants,
+    // The above, writing `OutlinePrecision::High` textures.
+    high_precision: JfaInitVariants,
+}
+
+pub(crate) struct JfaInitVariants {
+    pub(crate) cached: CachedRenderPipelineId,
+    // Variant which only seeds from the boundary of the mask, so that interior
+    // fragments receive the distance to it.
+    edge_seeds: CachedRenderPipelineId,
+    // Variant which also seeds from object ID discontinuities.
+    separate_objects: CachedRenderPipelineId,
+    // The above, reading multisampled object IDs.
+    multisampled_ids: CachedRenderPipelineId,
+}
+
+impl JfaInitPipeline {
+    fn variants(&self, precision: OutlinePrecision) -> &JfaInitVariants {
+        match precision {
+            OutlinePrecision::Standard => &self.standard,
+            OutlinePrecision::High => &self.high_precision,
+        }
+    }
+
+    /// Returns the variant seeding the jump flood of a view.
+    pub(crate) fn id(
+        &self,
+        settings: &OutlineSettings,
+        needs_inner_distance: bool,
+    ) -> CachedRenderPipelineId {
+        let variants = self.variants(settings.precision);
+        if settings.multisampled_ids() {
+            variants.multisampled_ids
+        } else if settings.separate_objects {
+            variants.separate_objects
+        } else if needs_inner_distance {
+            variants.edge_seeds
+        } else {
+            variants.cached
+        }
+    }
 }
 
 impl FromWorld for JfaInitPipeline {
@@ -24,42 +71,88 @@ impl FromWorld for JfaInitPipeline {
         let res = world.resource::<OutlineResources>();
         let dims_layout = res.dimensions_bind_group_layout.clone();
         let init_layout = res.jfa_init_bind_group_layout.clone();
+        let multisampled_ids_layout = res.jfa_init_multisampled_ids_bind_group_layout.clone();
 
         let pipeline_cache = world.get_resource_mut::<PipelineCache>().unwrap();
-        let cached = pipeline_cache.queue_render_pipeline(RenderPipelineDescriptor {
-            label: Some("outline_jfa_init_pipeline".into()),
-            layout: vec![dims_layout, init_layout],
-            vertex: VertexState {
-                shader: JFA_INIT_SHADER_HANDLE.typed::<Shader>(),
-                shader_defs: vec![],
-                entry_point: "vertex".into(),
-                buffers: vec![],
-            },
-            primitive: PrimitiveState {
-                topology: PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: FrontFace::Ccw,
-                cull_mode: Some(Face::Back),
-                unclipped_depth: false,
-                polygon_mode: PolygonMode::Fill,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: MultisampleState::default(),
-            fragment: Some(FragmentState {
-                shader: JFA_INIT_SHADER_HANDLE.typed::<Shader>(),
-                shader_defs: vec![],
-                entry_point: "fragment".into(),
-                targets: vec![Some(ColorTargetState {
-                    format: JFA_TEXTURE_FORMAT,
-                    blend: None,
-                    write_mask: ColorWrites::ALL,
-                })],
-            }),
-            push_constant_ranges: vec![],
-        });
-
-        JfaInitPipeline { cached }
+        let [standard, high_precision] =
+            [OutlinePrecision::Standard, OutlinePrecision::High].map(|precision| {
+                let [cached, edge_seeds, separate_objects, multisampled_ids] = [
+                    (&init_layout, vec![]),
+                    (&init_layout, vec!["EDGE_SEEDS".into()]),
+                    (
+                        &init_layout,
+                        vec!["EDGE_SEEDS".into(), "SEPARATE_OBJECTS".into()],
+                    ),
+                    (
+                        &multisampled_ids_layout,
+                        vec![
+                            "EDGE_SEEDS".into(),
+                            "SEPARATE_OBJECTS".into(),
+                            "MULTISAMPLED_IDS".into(),
+                        ],
+                    ),
+                ]
+                .map(|(layout, shader_defs)| {
+                    pipeline_cache.queue_render_pipeline(jfa_init_pipeline_descriptor(
+                        dims_layout.clone(),
+                        layout.clone(),
+                        shader_defs,
+                        precision.jfa_texture_format(),
+                    ))
+                });
+
+                JfaInitVariants {
+                    cached,
+                    edge_seeds,
+                    separate_objects,
+                    multisampled_ids,
+                }
+            });
+
+        JfaInitPipeline {
+            standard,
+            high_precision,
+        }
+    }
+}
+
+fn jfa_init_pipeline_descriptor(
+    dims_layout: BindGroupLayout,
+    init_layout: BindGroupLayout,
+    shader_defs: Vec<ShaderDefVal>,
+    format: TextureFormat,
+) -> RenderPipelineDescriptor {
+    RenderPipelineDescriptor {
+        label: Some("outline_jfa_init_pipeline".into()),
+        layout: vec![dims_layout, init_layout],
+        vertex: VertexState {
+            shader: JFA_INIT_SHADER_HANDLE.typed::<Shader>(),
+            shader_defs: vec![],
+            entry_point: "vertex".into(),
+            buffers: vec![],
+        },
+        primitive: PrimitiveState {
+            topology: PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: FrontFace::Ccw,
+            cull_mode: Some(Face::Back),
+            unclipped_depth: false,
+            polygon_mode: PolygonMode::Fill,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: MultisampleState::default(),
+        fragment: Some(FragmentState {
+            shader: JFA_INIT_SHADER_HANDLE.typed::<Shader>(),
+            shader_defs,
+            entry_point: "fragment".into(),
+            targets: vec![Some(ColorTargetState {
+                format,
+                blend: None,
+                write_mask: ColorWrites::ALL,
+            })],
+        }),
+        push_constant_ranges: vec![],
     }
 }
 
@@ -67,6 +160,8 @@ impl FromWorld for JfaInitPipeline {
 pub struct JfaInitNode;
 
 impl JfaInitNode {
+    pub const IN_VIEW: &'static str = "in_view";
+
     /// The input stencil buffer.
     ///
     /// This should have the format `TextureFormat::Depth24PlusStencil8`.
@@ -77,7 +172,7 @@ impl JfaInitNode {
 
     /// The produced initialized JFA buffer.
     ///
-    /// This has the format `bevy_jfa::JFA_TEXTURE_FORMAT`. Fragments that pass
+    /// This has the format of the view's [`OutlinePrecision`]. Fragments that pass
     /// the stencil test are assigned their framebuffer coordinates. Fragments
     /// that fail the stencil test are assigned a value of (-1, -1).
     pub const OUT_JFA_INIT: &'static str = "out_jfa_init";
@@ -85,7 +180,10 @@ impl JfaInitNode {
 
 impl Node for JfaInitNode {
     fn input(&self) -> Vec<SlotInfo> {
-        vec![SlotInfo::new(Self::IN_MASK, SlotType::TextureView)]
+        vec![
+            SlotInfo::new(Self::IN_VIEW, SlotType::Entity),
+            SlotInfo::new(Self::IN_MASK, SlotType::TextureView),
+        ]
     }
 
     fn output(&self) -> Vec<SlotInfo> {
@@ -99,16 +197,31 @@ impl Node for JfaInitNode {
         world: &World,
     ) -> Result<(), NodeRunError> {
         let res = world.get_resource::<OutlineResources>().unwrap();
-        graph
-            .set_output(
-                Self::OUT_JFA_INIT,
-                res.jfa_primary_output.default_view.clone(),
-            )
-            .unwrap();
-
-        let pipeline = world.get_resource::<JfaInitPipeline>().unwrap();
+        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
+        let (Some(settings), Some(textures)) = (
+            world.get::<OutlineSettings>(view_entity),
+            world.get::<ViewOutlineTextures>(view_entity),
+        ) else {
+            graph.set_output(Self::OUT_JFA_INIT, res.placeholder.default_view.clone())?;
+            return Ok(());
+        };
+        graph.set_output(
+            Self::OUT_JFA_INIT,
+            textures.jfa_primary_output.default_view.clone(),
+        )?;
+        if world.get::<ReuseOutlineFlood>(view_entity).is_some() {
+            return Ok(());
+        }
+
+        let needs_inner_distance = world
+            .get::<ViewOutlineParams>(view_entity)
+            .is_some_and(|outline| outline.params.needs_inner_distance());
+
+        let pipeline_id = world
+            .resource::<JfaInitPipeline>()
+            .id(settings, needs_inner_distance);
         let pipeline_cache = world.get_resource::<PipelineCache>().unwrap();
-        let cached_pipeline = match pipeline_cache.get_render_pipeline(pipeline.cached) {
+        let cached_pipeline = match pipeline_cache.get_render_pipeline(pipeline_id) {
             Some(c) => c,
             // Still queued.
             None => {
@@ -116,30 +229,39 @@ impl Node for JfaInitNode {
             }
         };
 
-        let mut tracked_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
-            label: Some("outline_jfa_init"),
-            color_attachments: &[Some(RenderPassColorAttachment {
-                view: &res.jfa_primary_output.default_view,
-                resolve_target: None,
-                ops: Operations {
-                    load: LoadOp::Clear(
-                        Color::RgbaLinear {
-                            red: -1.0,
-                            green: -1.0,
-                            blue: 0.0,
-                            alpha: 0.0,
-                        }
-                        .into(),
-                    ),
-                    store: true,
-                },
-            })],
-            depth_stencil_attachment: None,
-        });
-        tracked_pass.set_render_pipeline(cached_pipeline);
-        tracked_pass.set_bind_group(0, &res.dimensions_bind_group, &[]);
-        tracked_pass.set_bind_group(1, &res.jfa_init_bind_group, &[]);
-        tracked_pass.draw(0..3, 0..1);
+        time_pass(
+            world,
+            render_context,
+            view_entity,
+            OutlinePass::JfaInit,
+            |render_context| {
+                let mut tracked_pass =
+                    render_context.begin_tracked_render_pass(RenderPassDescriptor {
+                        label: Some("outline_jfa_init"),
+                        color_attachments: &[Some(RenderPassColorAttachment {
+                            view: &textures.jfa_primary_output.default_view,
+                            resolve_target: None,
+                            ops: Operations {
+                                load: LoadOp::Clear(
+                                    Color::RgbaLinear {
+                                        red: -1.0,
+                                        green: -1.0,
+                                        blue: 0.0,
+                                        alpha: 0.0,
+                                    }
+                                    .into(),
+                                ),
+                                store: true,
+                            },
+                        })],
+                        depth_stencil_attachment: None,
+                    });
+                tracked_pass.set_render_pipeline(cached_pipeline);
+                tracked_pass.set_bind_group(0, &textures.dimensions_bind_group, &[]);
+                tracked_pass.set_bind_group(1, &textures.jfa_init_bind_group, &[]);
+                tracked_pass.draw(0..3, 0..1);
+            },
+        );
 
         Ok(())
     }
@@ -2,7 +2,8 @@
 //!
 //! The **jump flooding algorithm** (JFA) is a fast screen-space algorithm for
 //! computing distance fields. Currently, this crate provides a plugin for
-//! adding outlines to arbitrary meshes.
+//! adding outlines to arbitrary meshes, and for generating distance fields
+//! from images with a [`JfaImageRequest`].
 //!
 //! Outlines adapted from ["The Quest for Very Wide Outlines" by Ben Golus][0].
 //!
@@ -18,47 +19,137 @@
 //!    camera which should render the outline.  Currently, outline styling is
 //!    tied to the camera rather than the mesh.
 //! 4. Add an [`Outline`] component to the mesh with `enabled: true`.
+//!
+//! With the `picking` feature, the [`OutlinePickingPlugin`] outlines entities
+//! under the cursor with an `OutlineOnHover`, or selected by clicking with an
+//! `OutlineOnSelect`.
+//!
+//! By default, outlines are drawn before bloom and tonemapping. Set
+//! [`OutlinePlugin::placement`] to run them elsewhere in the `core_3d` render
+//! graph. The labels of the outline nodes are in the [`graph`] module.
+//!
+//! To animate style changes on a camera, or fade outlines in and out when
+//! [`Outline::enabled`] changes, add an [`OutlineTransition`].
+//!
+//! Outline styles can also be loaded from `.outline.ron` files with the
+//! [`AssetServer`](bevy::asset::AssetServer). See [`OutlineStyleLoader`] for
+//! the format.
+//!
+//! To outline every mesh in a hierarchy, such as a spawned glTF scene, add
+//! [`OutlinePropagation::Descendants`] alongside the [`Outline`] on the root
+//! entity.
+//!
+//! The cost and quality of the outline passes are set by the
+//! [`OutlineSettings`] resource, or per camera by an `OutlineSettings`
+//! component.
+//!
+//! Per-frame statistics of the outline passes are recorded in
+//! [`OutlineStats`]. With the `gpu-timings` feature, GPU timings of the
+//! passes are recorded in [`OutlineGpuTimings`]. Both are reported as
+//! `Diagnostics` by the [`OutlineDiagnosticsPlugin`].
+//!
+//! Problems found while rendering, such as a camera whose style was removed,
+//! don't stop the app. Each is logged once as an [`OutlineError`] warning, and
+//! the affected outlines are skipped.
 
 use bevy::{
     app::prelude::*,
-    asset::{Assets, Handle, HandleUntyped},
+    asset::{AssetEvent, AssetServer, Assets, Handle, HandleId, HandleUntyped, LoadState},
     core_pipeline::core_3d,
-    ecs::{prelude::*, system::SystemParamItem},
-    pbr::{DrawMesh, MeshPipelineKey, MeshUniform, SetMeshBindGroup, SetMeshViewBindGroup},
+    ecs::{
+        prelude::*,
+        reflect::{ReflectComponent, ReflectResource},
+        system::SystemParamItem,
+    },
+    math::Vec2,
+    pbr::{MeshPipelineKey, MeshUniform, SetMeshBindGroup, SetMeshViewBindGroup},
     prelude::{AddAsset, Camera3d},
-    reflect::TypeUuid,
+    reflect::{prelude::*, ReflectRef, TypeUuid},
     render::{
+        extract_component::UniformComponentPlugin,
         extract_resource::ExtractResource,
+        main_graph,
         prelude::*,
-        render_asset::{PrepareAssetError, RenderAsset, RenderAssetPlugin, RenderAssets},
+        primitives::Aabb,
+        render_asset::{
+            PrepareAssetError, PrepareAssetSet, RenderAsset, RenderAssetPlugin, RenderAssets,
+        },
         render_graph::RenderGraph,
         render_phase::{
             AddRenderCommand, CachedRenderPipelinePhaseItem, DrawFunctionId, DrawFunctions,
             PhaseItem, RenderPhase, SetItemPipeline,
         },
         render_resource::*,
-        renderer::{RenderDevice, RenderQueue},
-        view::{ExtractedView, VisibleEntities},
+        view::{
+            ComputedVisibility, ExtractedView, RenderLayers, VisibilitySystems, VisibleEntities,
+        },
         Extract, RenderApp, RenderSet,
     },
-    utils::FloatOrd,
+    time::Time,
+    transform::components::GlobalTransform,
+    utils::{FloatOrd, HashMap, HashSet},
 };
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    graph::OutlineDriverNode,
-    mask::MeshMaskPipeline,
+    error::OutlineWarnings,
+    image_request::JfaImageNode,
+    mask::{
+        DrawOutlineMesh, MeshMaskPipeline, MeshMaskPipelineKey, SetDimensionsBindGroup,
+        SetOutlineMeshBindGroup,
+    },
     outline::{GpuOutlineParams, OutlineParams},
-    resources::OutlineResources,
+    transition::OutlineFade,
+};
+
+pub use crate::{
+    diagnostics::OutlineDiagnosticsPlugin,
+    error::OutlineError,
+    graph::{OutlineAnchor, OutlinePlacement},
+    hierarchy::{InheritedOutline, OutlinePropagation},
+    image_request::{JfaImageRequest, JfaImageRequestComplete},
+    loader::OutlineStyleLoader,
+    stats::{OutlineStats, ViewOutlineStats},
+    timings::{OutlineGpuTimings, OutlinePass, OutlinePassTiming},
+    transition::{OutlineEasing, OutlineTransition},
 };
 
-mod graph;
+#[cfg(feature = "picking")]
+pub use crate::picking::{
+    OutlineHover, OutlineOnHover, OutlineOnSelect, OutlinePickingPlugin, OutlineSelected,
+};
+
+pub mod cpu;
+
+mod diagnostics;
+mod error;
+#[cfg(feature = "gpu-timings")]
+mod gpu_timer;
+pub mod graph;
+mod hierarchy;
+mod hull;
+mod image_request;
 mod jfa;
 mod jfa_init;
+mod loader;
 mod mask;
 mod outline;
+mod overscan;
+#[cfg(feature = "picking")]
+mod picking;
 mod resources;
+mod stats;
+mod temporal;
+mod timings;
+mod transition;
 
 const JFA_TEXTURE_FORMAT: TextureFormat = TextureFormat::Rg16Snorm;
+// Coverage in the red channel, coverage-weighted outline opacity in the green
+// channel.
+const MASK_TEXTURE_FORMAT: TextureFormat = TextureFormat::Rg8Unorm;
+const OBJECT_ID_TEXTURE_FORMAT: TextureFormat = TextureFormat::R16Float;
+// World-space normal in the RGB channels, view-space depth in the alpha channel.
+const NORMAL_DEPTH_TEXTURE_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
 const FULLSCREEN_PRIMITIVE_STATE: PrimitiveState = PrimitiveState {
     topology: PrimitiveTopology::TriangleList,
     strip_index_format: None,
@@ -70,31 +161,263 @@ const FULLSCREEN_PRIMITIVE_STATE: PrimitiveState = PrimitiveState {
 };
 
 /// Top-level plugin for enabling outlines.
+///
+/// Without a `RenderApp`, as in headless apps using `MinimalPlugins`, the
+/// outline components and resources are still registered, but nothing is
+/// rendered. [`OutlineStyle`] assets are only available with an
+/// `AssetServer`.
 #[derive(Default)]
-pub struct OutlinePlugin;
+pub struct OutlinePlugin {
+    /// Where the outline passes run in the `core_3d` render graph.
+    pub placement: OutlinePlacement,
+}
 
 /// Performance and visual quality settings for JFA-based outlines.
-#[derive(Clone, ExtractResource, Resource)]
+///
+/// The resource applies to every camera with a [`CameraOutline`]. Adding the
+/// settings as a component to a camera overrides the resource for that camera,
+/// so that, for example, a minimap can be outlined at a lower resolution than
+/// the main view.
+#[derive(Clone, Debug, PartialEq, Component, ExtractResource, Resource, Reflect, FromReflect)]
+#[reflect(Component, Resource, Default)]
 pub struct OutlineSettings {
-    pub(crate) half_resolution: bool,
+    pub(crate) resolution_scale: f32,
+    pub(crate) msaa: bool,
+    pub(crate) quality: OutlineQuality,
+    pub(crate) precision: OutlinePrecision,
+    pub(crate) temporal: bool,
+    pub(crate) separate_objects: bool,
+    pub(crate) creases: bool,
+    pub(crate) method: OutlineMethod,
+    pub(crate) overscan: bool,
 }
 
 impl OutlineSettings {
+    // Smallest supported resolution scale.
+    const MIN_RESOLUTION_SCALE: f32 = 1.0 / 16.0;
+
+    /// Returns the scale of the jump flood resolution, relative to the view.
+    pub fn resolution_scale(&self) -> f32 {
+        self.resolution_scale
+    }
+
+    /// Sets the scale of the jump flood resolution, relative to the view.
+    ///
+    /// Lower scales flood fewer pixels, at the cost of blockier outlines. The
+    /// scale is clamped to between 1/16 and 1. Widths and distances of the
+    /// [`OutlineStyle`] remain in pixels of the view whatever the scale.
+    pub fn set_resolution_scale(&mut self, value: f32) {
+        self.resolution_scale = if value.is_nan() {
+            1.0
+        } else {
+            value.clamp(Self::MIN_RESOLUTION_SCALE, 1.0)
+        };
+    }
+
     /// Returns whether the half-resolution setting is enabled.
     pub fn half_resolution(&self) -> bool {
-        self.half_resolution
+        self.resolution_scale == 0.5
     }
 
     /// Sets whether the half-resolution setting is enabled.
+    ///
+    /// This is equivalent to a [resolution scale](Self::set_resolution_scale)
+    /// of 0.5 when enabled, and 1 when disabled.
     pub fn set_half_resolution(&mut self, value: bool) {
-        self.half_resolution = value;
+        self.resolution_scale = if value { 0.5 } else { 1.0 };
+    }
+
+    /// Returns whether the mask is multisampled.
+    pub fn msaa(&self) -> bool {
+        self.msaa
+    }
+
+    /// Sets whether the mask is multisampled.
+    ///
+    /// When enabled, the mask is rendered with 4 samples per pixel, which
+    /// smooths the edges of outlines. This is independent of the `Msaa` used
+    /// by the camera.
+    pub fn set_msaa(&mut self, value: bool) {
+        self.msaa = value;
+    }
+
+    /// Returns the quality of the jump flood.
+    pub fn quality(&self) -> OutlineQuality {
+        self.quality
+    }
+
+    /// Sets the quality of the jump flood.
+    pub fn set_quality(&mut self, value: OutlineQuality) {
+        self.quality = value;
+    }
+
+    /// Returns the precision of the jump flood textures.
+    pub fn precision(&self) -> OutlinePrecision {
+        self.precision
+    }
+
+    /// Sets the precision of the jump flood textures.
+    pub fn set_precision(&mut self, value: OutlinePrecision) {
+        self.precision = value;
+    }
+
+    /// Returns whether the previous frame's jump flood is reused.
+    pub fn temporal(&self) -> bool {
+        self.temporal
+    }
+
+    /// Sets whether the previous frame's jump flood is reused.
+    ///
+    /// When enabled, seeds still present from the previous frame are kept,
+    /// and only a few short jump flood passes are run to correct for motion.
+    /// This makes wide outlines in near-static views much cheaper. The previous
+    /// flood is searched at the same screen position rather than reprojected,
+    /// so a full jump flood is still run whenever a corner of an outlined
+    /// object's bounds moves more than 8 pixels on screen, including during
+    /// camera pans, and when objects appear or disappear or the outline grows.
+    pub fn set_temporal(&mut self, value: bool) {
+        self.temporal = value;
+    }
+
+    /// Returns whether the separate-objects setting is enabled.
+    pub fn separate_objects(&self) -> bool {
+        self.separate_objects
+    }
+
+    /// Sets whether the separate-objects setting is enabled.
+    ///
+    /// When enabled, each outlined object writes its ID into the mask, and a
+    /// line of [`OutlineStyle::contour_width`] is drawn wherever two outlined
+    /// objects touch or overlap. When disabled, adjacent objects merge into a
+    /// single silhouette.
+    ///
+    /// The meshes sharing an [`Outline`] through [`OutlinePropagation`] are a
+    /// single object. Only 2047 objects have distinct IDs; beyond that, IDs are
+    /// reused, and objects sharing one have no contour between them.
+    pub fn set_separate_objects(&mut self, value: bool) {
+        self.separate_objects = value;
+    }
+
+    /// Returns whether lines are drawn along creases inside outlined objects.
+    pub fn creases(&self) -> bool {
+        self.creases
+    }
+
+    /// Sets whether lines are drawn along creases inside outlined objects.
+    ///
+    /// When enabled, the normals and depths of outlined meshes are rendered
+    /// alongside the mask, and [`OutlineStyle::creases`] are drawn wherever
+    /// they change sharply, such as the folds and inner edges of a building.
+    /// Meshes without normals only have creases where their depth changes.
+    pub fn set_creases(&mut self, value: bool) {
+        self.creases = value;
+    }
+
+    /// Returns how outlines are drawn.
+    pub fn method(&self) -> OutlineMethod {
+        self.method
+    }
+
+    /// Sets how outlines are drawn.
+    ///
+    /// Apart from the [overscan](Self::set_overscan), the other settings only
+    /// apply to the jump flood.
+    pub fn set_method(&mut self, value: OutlineMethod) {
+        self.method = value;
+    }
+
+    /// Returns whether objects just outside the view are outlined.
+    pub fn overscan(&self) -> bool {
+        self.overscan
+    }
+
+    /// Sets whether objects just outside the view are outlined.
+    ///
+    /// When enabled, outlined objects culled from the view are still drawn if
+    /// their outline or shadow could reach into it, and the mask and jump
+    /// flood extend past the edges of the view by as much. Outlines then slide
+    /// in smoothly from the edges of the view, rather than appearing once the
+    /// objects are visible. The guard band is at most 256 pixels wide.
+    pub fn set_overscan(&mut self, value: bool) {
+        self.overscan = value;
+    }
+
+    // Number of samples per pixel of the mask.
+    pub(crate) fn mask_samples(&self) -> u32 {
+        if self.msaa {
+            4
+        } else {
+            1
+        }
+    }
+
+    // Whether object IDs are read from a multisampled texture.
+    pub(crate) fn multisampled_ids(&self) -> bool {
+        self.separate_objects && self.mask_samples() > 1
     }
 }
 
 impl Default for OutlineSettings {
     fn default() -> Self {
         Self {
-            half_resolution: false,
+            resolution_scale: 1.0,
+            msaa: true,
+            quality: OutlineQuality::default(),
+            precision: OutlinePrecision::default(),
+            temporal: false,
+            separate_objects: false,
+            creases: false,
+            method: OutlineMethod::default(),
+            overscan: false,
+        }
+    }
+}
+
+/// Technique used to draw outlines.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect, FromReflect)]
+pub enum OutlineMethod {
+    /// Outlines are drawn from a jump flood of the mask of outlined meshes,
+    /// supporting every feature of the [`OutlineStyle`].
+    #[default]
+    JumpFlood,
+    /// Outlines are drawn as the back faces of outlined meshes, extruded along
+    /// their normals by the outline width.
+    ///
+    /// This takes a single mesh pass, which is much cheaper than the jump
+    /// flood for thin outlines on many objects. Only the color and width of
+    /// the [`OutlineStyle`] are used, and skinned meshes and meshes without
+    /// normals aren't outlined. Meshes with split normals, such as cubes, show gaps in the
+    /// outline at their hard edges.
+    Hull,
+}
+
+/// Number of jump flood passes run for outlines.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect, FromReflect)]
+pub enum OutlineQuality {
+    /// One pass for each power-of-two jump distance.
+    #[default]
+    Standard,
+    /// An extra pass with a jump distance of one pixel, which corrects most of
+    /// the errors of the jump flood near the edges of objects.
+    High,
+}
+
+/// Format of the jump flood textures.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect, FromReflect)]
+pub enum OutlinePrecision {
+    /// 16-bit normalized coordinates, with subpixel precision for views up to
+    /// a few thousand pixels wide.
+    #[default]
+    Standard,
+    /// 32-bit float coordinates, at twice the memory and bandwidth.
+    High,
+}
+
+impl OutlinePrecision {
+    pub(crate) fn jfa_texture_format(self) -> TextureFormat {
+        match self {
+            OutlinePrecision::Standard => JFA_TEXTURE_FORMAT,
+            OutlinePrecision::High => TextureFormat::Rg32Float,
         }
     }
 }
@@ -111,16 +434,90 @@ const OUTLINE_SHADER_HANDLE: HandleUntyped =
     HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 11094028876979933159);
 const DIMENSIONS_SHADER_HANDLE: HandleUntyped =
     HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 11721531257850828867);
-
-use crate::graph::outline as outline_graph;
+const JFA_DISTANCE_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 6417953048211862419);
+const JFA_TEMPORAL_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 15254946105523413570);
+const HULL_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 9371150284626093317);
 
 impl Plugin for OutlinePlugin {
     fn build(&self, app: &mut App) {
+        // Headless apps without an `AssetServer` can't store styles, but can
+        // still use the outline components.
+        if app.world.contains_resource::<AssetServer>() {
+            app.add_asset::<OutlineStyle>()
+                .init_asset_loader::<OutlineStyleLoader>();
+        }
+
         app.add_plugin(RenderAssetPlugin::<OutlineStyle>::default())
-            .add_asset::<OutlineStyle>()
-            .init_resource::<OutlineSettings>();
+            .register_asset_reflect::<OutlineStyle>()
+            .register_type::<Outline>()
+            .register_type::<CameraOutline>()
+            .register_type::<OutlineShadow>()
+            .register_type::<Option<OutlineShadow>>()
+            .register_type::<OutlineFill>()
+            .register_type::<Option<OutlineFill>>()
+            .register_type::<OutlineCreases>()
+            .register_type::<Option<OutlineCreases>>()
+            .register_type::<OutlineFillPattern>()
+            .register_type::<OutlineSettings>()
+            .register_type::<OutlineQuality>()
+            .register_type::<OutlinePrecision>()
+            .register_type::<OutlineMethod>()
+            .register_type::<OutlinePropagation>()
+            .register_type::<InheritedOutline>()
+            .register_type::<OutlineProxy>()
+            .register_type::<OutlineTransition>()
+            .register_type::<OutlineEasing>()
+            .register_type::<JfaImageRequest>()
+            .init_resource::<OutlineSettings>()
+            .init_resource::<image_request::CompletedImageRequests>()
+            .init_resource::<OutlineGpuTimings>()
+            .init_resource::<timings::SharedGpuTimings>()
+            .init_resource::<OutlineStats>()
+            .init_resource::<stats::SharedOutlineStats>()
+            .add_event::<JfaImageRequestComplete>()
+            .add_system(image_request::send_image_request_events.in_base_set(CoreSet::First))
+            .add_system(timings::update_gpu_timings.in_base_set(CoreSet::First))
+            .add_system(stats::update_outline_stats.in_base_set(CoreSet::First))
+            .add_system(
+                hierarchy::propagate_outlines
+                    .in_base_set(CoreSet::PostUpdate)
+                    .run_if(hierarchy::outlines_changed),
+            )
+            .add_system(
+                transition::update_style_blends
+                    .in_base_set(CoreSet::PostUpdate)
+                    .run_if(resource_exists::<Time>())
+                    .run_if(resource_exists::<Assets<OutlineStyle>>()),
+            )
+            .add_system(
+                transition::update_outline_fades
+                    .in_base_set(CoreSet::PostUpdate)
+                    .run_if(resource_exists::<Time>()),
+            )
+            .add_system(transition::remove_outline_fades.in_base_set(CoreSet::PostUpdate));
+
+        if app.get_sub_app(RenderApp).is_err() {
+            return;
+        }
+
+        app.init_resource::<OutlineProxyBounds>()
+            .add_system(
+                update_proxy_bounds
+                    .in_base_set(CoreSet::PostUpdate)
+                    .run_if(resource_exists::<Assets<Mesh>>()),
+            )
+            .add_system(
+                overscan::expand_outline_visibility
+                    .in_base_set(CoreSet::PostUpdate)
+                    .after(VisibilitySystems::CheckVisibility)
+                    .after(hierarchy::propagate_outlines)
+                    .after(update_proxy_bounds),
+            );
 
-        let mut shaders = app.world.get_resource_mut::<Assets<Shader>>().unwrap();
+        let mut shaders = app.world.resource_mut::<Assets<Shader>>();
 
         let mask_shader = Shader::from_wgsl(include_str!("shaders/mask.wgsl"));
         let jfa_init_shader = Shader::from_wgsl(include_str!("shaders/jfa_init.wgsl"));
@@ -130,6 +527,9 @@ impl Plugin for OutlinePlugin {
         let outline_shader = Shader::from_wgsl(include_str!("shaders/outline.wgsl"));
         let dimensions_shader = Shader::from_wgsl(include_str!("shaders/dimensions.wgsl"))
             .with_import_path("outline::dimensions");
+        let jfa_distance_shader = Shader::from_wgsl(include_str!("shaders/jfa_distance.wgsl"));
+        let jfa_temporal_shader = Shader::from_wgsl(include_str!("shaders/jfa_temporal.wgsl"));
+        let hull_shader = Shader::from_wgsl(include_str!("shaders/hull.wgsl"));
 
         shaders.set_untracked(MASK_SHADER_HANDLE, mask_shader);
         shaders.set_untracked(JFA_INIT_SHADER_HANDLE, jfa_init_shader);
@@ -137,44 +537,100 @@ impl Plugin for OutlinePlugin {
         shaders.set_untracked(FULLSCREEN_SHADER_HANDLE, fullscreen_shader);
         shaders.set_untracked(OUTLINE_SHADER_HANDLE, outline_shader);
         shaders.set_untracked(DIMENSIONS_SHADER_HANDLE, dimensions_shader);
+        shaders.set_untracked(JFA_DISTANCE_SHADER_HANDLE, jfa_distance_shader);
+        shaders.set_untracked(JFA_TEMPORAL_SHADER_HANDLE, jfa_temporal_shader);
+        shaders.set_untracked(HULL_SHADER_HANDLE, hull_shader);
 
-        let render_app = match app.get_sub_app_mut(RenderApp) {
-            Ok(r) => r,
-            Err(_) => return,
-        };
+        let completed_image_requests = app
+            .world
+            .resource::<image_request::CompletedImageRequests>()
+            .clone();
+        let shared_gpu_timings = app.world.resource::<timings::SharedGpuTimings>().clone();
+        let shared_outline_stats = app.world.resource::<stats::SharedOutlineStats>().clone();
+
+        let render_app = app.sub_app_mut(RenderApp);
 
         render_app
+            .init_resource::<OutlineWarnings>()
             .init_resource::<DrawFunctions<MeshMask>>()
             .add_render_command::<MeshMask, SetItemPipeline>()
             .add_render_command::<MeshMask, DrawMeshMask>()
+            .init_resource::<DrawFunctions<hull::HullOutline>>()
+            .add_render_command::<hull::HullOutline, hull::DrawHullOutline>()
+            .add_plugin(UniformComponentPlugin::<OutlineMeshUniform>::default())
             .init_resource::<resources::OutlineResources>()
             .init_resource::<mask::MeshMaskPipeline>()
             .init_resource::<SpecializedMeshPipelines<mask::MeshMaskPipeline>>()
+            .init_resource::<hull::HullPipeline>()
+            .init_resource::<SpecializedMeshPipelines<hull::HullPipeline>>()
             .init_resource::<jfa_init::JfaInitPipeline>()
             .init_resource::<jfa::JfaPipeline>()
             .init_resource::<outline::OutlinePipeline>()
             .init_resource::<SpecializedRenderPipelines<outline::OutlinePipeline>>()
-            .add_system(extract_outline_settings.in_schedule(ExtractSchedule))
+            .init_resource::<outline::ViewOutlineUniforms>()
+            .init_resource::<image_request::JfaDistancePipeline>()
+            .init_resource::<SpecializedRenderPipelines<image_request::JfaDistancePipeline>>()
+            .init_resource::<image_request::ImageRequests>()
+            .insert_resource(completed_image_requests)
+            .insert_resource(shared_gpu_timings)
+            .insert_resource(shared_outline_stats)
+            .add_system(warn_invalid_styles.in_schedule(ExtractSchedule))
             .add_system(extract_camera_outlines.in_schedule(ExtractSchedule))
+            .add_system(transition::extract_style_blends.in_schedule(ExtractSchedule))
+            .add_system(extract_outlines.in_schedule(ExtractSchedule))
+            .add_system(overscan::extract_overscan_entities.in_schedule(ExtractSchedule))
             .add_system(extract_mask_camera_phase.in_schedule(ExtractSchedule))
-            .add_system(resources::recreate_outline_resources.in_set(RenderSet::Queue))
-            .add_system(queue_mesh_masks.in_set(RenderSet::Queue));
+            .add_system(image_request::extract_image_requests.in_schedule(ExtractSchedule))
+            .add_system(
+                outline::prepare_view_outline_params
+                    .in_set(RenderSet::Prepare)
+                    .after(PrepareAssetSet::AssetPrepare),
+            )
+            .add_system(
+                image_request::prepare_image_requests
+                    .in_set(RenderSet::Prepare)
+                    .after(PrepareAssetSet::AssetPrepare),
+            )
+            .add_system(
+                resources::prepare_view_outline_textures
+                    .in_set(RenderSet::Prepare)
+                    .after(PrepareAssetSet::AssetPrepare),
+            )
+            .add_system(hull::prepare_hull_depth.in_set(RenderSet::Prepare))
+            .add_system(image_request::finish_image_requests.in_set(RenderSet::Cleanup))
+            .add_system(mask::queue_outline_mesh_bind_group.in_set(RenderSet::Queue))
+            .add_system(queue_mesh_masks.in_set(RenderSet::Queue))
+            .add_system(hull::queue_hull_outlines.in_set(RenderSet::Queue))
+            .add_system(
+                temporal::prepare_flood_reuse
+                    .in_set(RenderSet::Queue)
+                    .after(queue_mesh_masks),
+            )
+            .add_system(stats::collect_outline_stats.in_set(RenderSet::PhaseSort));
+
+        #[cfg(feature = "gpu-timings")]
+        render_app
+            .init_resource::<gpu_timer::GpuTimer>()
+            .add_system(gpu_timer::begin_gpu_timer_frame.in_set(RenderSet::Prepare))
+            .add_system(gpu_timer::end_gpu_timer_frame.in_set(RenderSet::Cleanup));
 
-        let outline_graph = graph::outline(render_app).unwrap();
+        let outline_graph = graph::build_outline_graph(render_app).unwrap();
 
         let mut root_graph = render_app.world.resource_mut::<RenderGraph>();
+        root_graph.add_node(JfaImageNode::NAME, JfaImageNode);
+        root_graph.add_node_edge(JfaImageNode::NAME, main_graph::node::CAMERA_DRIVER);
+        #[cfg(feature = "gpu-timings")]
+        {
+            root_graph.add_node(gpu_timer::GpuTimerNode::NAME, gpu_timer::GpuTimerNode);
+            root_graph.add_node_edge(
+                main_graph::node::CAMERA_DRIVER,
+                gpu_timer::GpuTimerNode::NAME,
+            );
+        }
+
         let draw_3d_graph = root_graph.get_sub_graph_mut(core_3d::graph::NAME).unwrap();
-        let draw_3d_input = draw_3d_graph.input_node().id;
-
-        draw_3d_graph.add_sub_graph(outline_graph::NAME, outline_graph);
-        let outline_driver = draw_3d_graph.add_node(OutlineDriverNode::NAME, OutlineDriverNode);
-        draw_3d_graph.add_slot_edge(
-            draw_3d_input,
-            core_3d::graph::input::VIEW_ENTITY,
-            outline_driver,
-            OutlineDriverNode::INPUT_VIEW,
-        );
-        draw_3d_graph.add_node_edge(core_3d::graph::node::MAIN_PASS, outline_driver);
+        draw_3d_graph.add_sub_graph(graph::outline::NAME, outline_graph);
+        self.placement.add_driver(draw_3d_graph);
     }
 }
 
@@ -211,131 +667,637 @@ type DrawMeshMask = (
     SetItemPipeline,
     SetMeshViewBindGroup<0>,
     SetMeshBindGroup<1>,
-    DrawMesh,
+    SetOutlineMeshBindGroup<2>,
+    SetDimensionsBindGroup<3>,
+    DrawOutlineMesh,
 );
 
 /// Visual style for an outline.
-#[derive(Clone, Debug, PartialEq, TypeUuid)]
+#[derive(Clone, Debug, PartialEq, TypeUuid, Reflect, FromReflect, Serialize, Deserialize)]
+#[reflect(Default)]
+#[serde(default)]
 #[uuid = "256fd556-e497-4df2-8d9c-9bdb1419ee90"]
 pub struct OutlineStyle {
     pub color: Color,
     pub width: f32,
+    /// Width in pixels of the lines drawn between adjacent outlined objects.
+    ///
+    /// Only used when [`OutlineSettings::separate_objects`] is enabled.
+    pub contour_width: f32,
+    /// Distance in pixels over which the inner and outer edges of the outline
+    /// fade out.
+    ///
+    /// Defaults to 1 for anti-aliased edges. Zero gives hard edges.
+    pub softness: f32,
+    /// Soft shadow drawn behind the outlined objects.
+    pub shadow: Option<OutlineShadow>,
+    /// Fill drawn over the interior of the outlined objects.
+    pub fill: Option<OutlineFill>,
+    /// Lines drawn along creases and depth discontinuities inside the
+    /// outlined objects.
+    ///
+    /// Only used when [`OutlineSettings::creases`] is enabled.
+    pub creases: Option<OutlineCreases>,
+}
+
+impl Default for OutlineStyle {
+    fn default() -> Self {
+        Self {
+            color: Color::WHITE,
+            width: 1.0,
+            contour_width: 1.0,
+            softness: 1.0,
+            shadow: None,
+            fill: None,
+            creases: None,
+        }
+    }
+}
+
+/// Fill drawn over the interior of outlined objects, such as a highlight on
+/// hover.
+#[derive(Clone, Debug, PartialEq, Reflect, FromReflect, Serialize, Deserialize)]
+#[reflect(Default)]
+#[serde(default)]
+pub struct OutlineFill {
+    /// Fill color. The alpha channel sets the opacity of the fill.
+    pub color: Color,
+    /// Distance in pixels over which the fill fades out from the edge of the
+    /// objects towards their interior, like an inner glow.
+    ///
+    /// If zero, the interior is filled evenly.
+    pub inner_distance: f32,
+    pub pattern: OutlineFillPattern,
+}
+
+impl Default for OutlineFill {
+    fn default() -> Self {
+        Self {
+            color: Color::rgba(1.0, 1.0, 1.0, 0.25),
+            inner_distance: 0.0,
+            pattern: OutlineFillPattern::Solid,
+        }
+    }
+}
+
+/// Screen-space pattern of an [`OutlineFill`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Reflect, FromReflect, Serialize, Deserialize)]
+pub enum OutlineFillPattern {
+    #[default]
+    Solid,
+    /// Diagonal stripes `width` pixels wide, repeating every `spacing` pixels.
+    Stripes { spacing: f32, width: f32 },
+    /// Dots of the given radius in pixels, on a grid repeating every `spacing`
+    /// pixels.
+    Dots { spacing: f32, radius: f32 },
+}
+
+/// Lines drawn inside outlined objects, where their surface folds or where one
+/// part of an object occludes another.
+#[derive(Clone, Debug, PartialEq, Reflect, FromReflect, Serialize, Deserialize)]
+#[reflect(Default)]
+#[serde(default)]
+pub struct OutlineCreases {
+    pub color: Color,
+    /// Width of the lines in pixels.
+    pub width: f32,
+    /// Change in the surface normal across a line, as the distance between
+    /// unit normals, from 0 to 2.
+    pub normal_threshold: f32,
+    /// Change in depth across a line, relative to the depth of the surface.
+    pub depth_threshold: f32,
+}
+
+impl Default for OutlineCreases {
+    fn default() -> Self {
+        Self {
+            color: Color::BLACK,
+            width: 1.0,
+            normal_threshold: 0.5,
+            depth_threshold: 0.1,
+        }
+    }
+}
+
+/// Drop shadow drawn behind outlined objects, and below their outline.
+#[derive(Clone, Debug, PartialEq, Reflect, FromReflect, Serialize, Deserialize)]
+#[reflect(Default)]
+#[serde(default)]
+pub struct OutlineShadow {
+    pub color: Color,
+    /// Offset in pixels of the shadow from the objects, with `y` pointing
+    /// down the screen.
+    pub offset: Vec2,
+    /// Distance in pixels over which the shadow fades out.
+    pub softness: f32,
+}
+
+impl Default for OutlineShadow {
+    fn default() -> Self {
+        Self {
+            color: Color::rgba(0.0, 0.0, 0.0, 0.5),
+            offset: Vec2::new(4.0, 4.0),
+            softness: 8.0,
+        }
+    }
+}
+
+impl OutlineStyle {
+    /// Checks that the widths, distances, pattern sizes and thresholds of the
+    /// style are non-negative numbers.
+    ///
+    /// Invalid values are treated as zero when rendering.
+    pub fn validate(&self) -> Result<(), OutlineError> {
+        // Fields the style doesn't have are zero.
+        let mut params = OutlineParams::from_style(self);
+        for (field, value) in style_widths(&mut params) {
+            validate_width(field, *value)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn validate_width(field: &'static str, value: f32) -> Result<(), OutlineError> {
+    if value.is_nan() || value < 0.0 {
+        return Err(OutlineError::InvalidStyleValue { field, value });
+    }
+
+    Ok(())
+}
+
+// Returns the fields of `params` which must be non-negative, named after those
+// of the style.
+fn style_widths(params: &mut OutlineParams) -> [(&'static str, &mut f32); 10] {
+    let pattern_size = match params.fill_pattern {
+        1 => "fill.pattern.width",
+        _ => "fill.pattern.radius",
+    };
+    [
+        ("width", &mut params.weight),
+        ("contour_width", &mut params.contour_weight),
+        ("softness", &mut params.softness),
+        ("shadow.softness", &mut params.shadow_softness),
+        ("fill.inner_distance", &mut params.fill_inner_distance),
+        ("fill.pattern.spacing", &mut params.fill_pattern_spacing),
+        (pattern_size, &mut params.fill_pattern_size),
+        ("creases.width", &mut params.crease_weight),
+        (
+            "creases.normal_threshold",
+            &mut params.crease_normal_threshold,
+        ),
+        (
+            "creases.depth_threshold",
+            &mut params.crease_depth_threshold,
+        ),
+    ]
+}
+
+/// Warns about invalid values of created or modified styles, which are
+/// replaced when preparing them.
+fn warn_invalid_styles(
+    warnings: Res<OutlineWarnings>,
+    styles: Extract<Res<Assets<OutlineStyle>>>,
+    mut events: Extract<EventReader<AssetEvent<OutlineStyle>>>,
+) {
+    for event in events.iter() {
+        let (AssetEvent::Created { handle } | AssetEvent::Modified { handle }) = event else {
+            continue;
+        };
+        let Some(style) = styles.get(handle) else {
+            continue;
+        };
+
+        let mut params = OutlineParams::from_style(style);
+        for (field, value) in style_widths(&mut params) {
+            if let Err(error) = validate_width(field, *value) {
+                warnings.warn_once_for_style(handle.id(), &error, "using 0 instead");
+            }
+        }
+    }
 }
 
 impl RenderAsset for OutlineStyle {
     type ExtractedAsset = OutlineParams;
     type PreparedAsset = GpuOutlineParams;
-    type Param = (
-        Res<'static, RenderDevice>,
-        Res<'static, RenderQueue>,
-        Res<'static, OutlineResources>,
-    );
+    type Param = ();
 
     fn extract_asset(&self) -> Self::ExtractedAsset {
-        OutlineParams::new(self.color, self.width)
+        OutlineParams::from_style(self)
     }
 
     fn prepare_asset(
-        extracted_asset: Self::ExtractedAsset,
-        (device, queue, outline_res): &mut SystemParamItem<Self::Param>,
+        mut extracted_asset: Self::ExtractedAsset,
+        _param: &mut SystemParamItem<Self::Param>,
     ) -> Result<Self::PreparedAsset, PrepareAssetError<Self::ExtractedAsset>> {
-        let mut buffer = UniformBuffer::from(extracted_asset.clone());
-        buffer.write_buffer(device, queue);
-
-        let bind_group = device.create_bind_group(&BindGroupDescriptor {
-            label: None,
-            layout: &outline_res.outline_params_bind_group_layout,
-            entries: &[BindGroupEntry {
-                binding: 0,
-                resource: buffer.buffer().unwrap().as_entire_binding(),
-            }],
-        });
+        // Invalid values are reported when extracting the style.
+        for (field, value) in style_widths(&mut extracted_asset) {
+            if validate_width(field, *value).is_err() {
+                *value = 0.0;
+            }
+        }
 
         Ok(GpuOutlineParams {
             params: extracted_asset,
-            _buffer: buffer,
-            bind_group,
         })
     }
 }
 
 /// Component for enabling outlines when rendering with a given camera.
-#[derive(Clone, Debug, PartialEq, Component)]
+#[derive(Clone, Debug, PartialEq, Component, Reflect)]
+#[reflect(Component, Default)]
 pub struct CameraOutline {
     pub enabled: bool,
     pub style: Handle<OutlineStyle>,
+    /// Layers whose outlined entities are outlined by this camera.
+    ///
+    /// An outlined entity is drawn if its [`RenderLayers`] intersect these
+    /// layers. Defaults to all layers, so every outlined entity visible to the
+    /// camera is outlined.
+    pub layers: RenderLayers,
+}
+
+impl Default for CameraOutline {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            style: Handle::default(),
+            layers: RenderLayers::all(),
+        }
+    }
+}
+
+// `RenderLayers` doesn't implement `FromReflect`, so the layers are applied
+// onto the default value instead.
+impl FromReflect for CameraOutline {
+    fn from_reflect(reflect: &dyn Reflect) -> Option<Self> {
+        let ReflectRef::Struct(value) = reflect.reflect_ref() else {
+            return None;
+        };
+
+        let mut outline = CameraOutline::default();
+        if let Some(enabled) = value.field("enabled") {
+            outline.enabled = bool::from_reflect(enabled)?;
+        }
+        if let Some(style) = value.field("style") {
+            outline.style = Handle::from_reflect(style)?;
+        }
+        if let Some(layers) = value.field("layers") {
+            outline.layers.apply(layers);
+        }
+
+        Some(outline)
+    }
 }
 
 /// Component for entities that should be outlined.
-#[derive(Clone, Debug, PartialEq, Component)]
+///
+/// To apply the outline to the entity's descendants as well, add
+/// [`OutlinePropagation::Descendants`].
+#[derive(Clone, Debug, PartialEq, Component, Reflect, FromReflect)]
+#[reflect(Component, Default)]
 pub struct Outline {
     pub enabled: bool,
 }
 
-fn extract_outline_settings(mut commands: Commands, settings: Extract<Res<OutlineSettings>>) {
-    commands.insert_resource(settings.clone());
+impl Default for Outline {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// Component for outlining an entity by the shape of another mesh.
+///
+/// The proxy mesh is drawn into the outline mask with the entity's transform,
+/// in place of the entity's own mesh, which is still rendered as usual. This
+/// allows outlining high-poly meshes by a simplified shape, or a character by
+/// a capsule around it. Entities with a proxy don't need a mesh of their own,
+/// only a transform and visibility such as from a
+/// [`SpatialBundle`](bevy::prelude::SpatialBundle).
+///
+/// Proxies aren't inherited, so each outlined descendant draws its own mesh
+/// unless it has a proxy too.
+#[derive(Clone, Debug, Default, PartialEq, Component, Reflect, FromReflect)]
+#[reflect(Component, Default)]
+pub struct OutlineProxy(pub Handle<Mesh>);
+
+/// Bounds of the meshes used as an [`OutlineProxy`].
+///
+/// Bevy only computes the bounds of an entity's own mesh, so those of proxies
+/// are computed here, once per mesh, for culling and extraction.
+#[derive(Default, Resource)]
+pub(crate) struct OutlineProxyBounds(HashMap<HandleId, Option<Aabb>>);
+
+impl OutlineProxyBounds {
+    pub(crate) fn get(&self, mesh: &Handle<Mesh>) -> Option<Aabb> {
+        self.0.get(&mesh.id()).copied().flatten()
+    }
+}
+
+fn update_proxy_bounds(
+    mut bounds: ResMut<OutlineProxyBounds>,
+    meshes: Res<Assets<Mesh>>,
+    mut events: EventReader<AssetEvent<Mesh>>,
+    proxies: Query<&OutlineProxy>,
+) {
+    for event in events.iter() {
+        let (AssetEvent::Created { handle }
+        | AssetEvent::Modified { handle }
+        | AssetEvent::Removed { handle }) = event;
+        bounds.0.remove(&handle.id());
+    }
+
+    for proxy in proxies.iter() {
+        bounds
+            .0
+            .entry(proxy.0.id())
+            .or_insert_with(|| meshes.get(&proxy.0).and_then(Mesh::compute_aabb));
+    }
+}
+
+/// Render world data for meshes with an enabled outline.
+#[derive(Clone, Debug, Component)]
+pub(crate) struct ExtractedOutline {
+    layers: RenderLayers,
+    // Mesh drawn into the mask, which is the proxy if there is one.
+    mesh: Handle<Mesh>,
+    // Bounds of `mesh`, if known.
+    aabb: Option<Aabb>,
+}
+
+/// Per-mesh uniform data for the mask pass.
+#[derive(Clone, Debug, PartialEq, Component, ShaderType)]
+pub struct OutlineMeshUniform {
+    // Object ID written to the mask when separating objects. Zero is reserved
+    // for empty space.
+    id: u32,
+    // Opacity of the object's outline, written alongside the mask coverage.
+    alpha: f32,
+}
+
+impl OutlineMeshUniform {
+    // IDs are stored as `OBJECT_ID_TEXTURE_FORMAT`, which represents integers
+    // exactly up to 2048.
+    const MAX_ID: u32 = 2048;
+
+    // Objects are given the IDs from 1 to `MAX_ID - 1` in turn.
+    fn new(index: usize, alpha: f32) -> Self {
+        Self {
+            id: (index as u32 % (Self::MAX_ID - 1)) + 1,
+            alpha,
+        }
+    }
 }
 
 fn extract_camera_outlines(
     mut commands: Commands,
     mut previous_outline_len: Local<usize>,
-    cam_outline_query: Extract<Query<(Entity, &CameraOutline), With<Camera>>>,
+    warnings: Res<OutlineWarnings>,
+    styles: Extract<Res<Assets<OutlineStyle>>>,
+    asset_server: Extract<Option<Res<AssetServer>>>,
+    default_settings: Extract<Res<OutlineSettings>>,
+    cam_outline_query: Extract<Query<CameraOutlineQuery, With<Camera>>>,
 ) {
+    // Cameras without a style are still extracted, and skipped when rendering.
+    for (_, outline, _) in cam_outline_query.iter() {
+        let loading = asset_server
+            .as_ref()
+            .is_some_and(|server| server.get_load_state(&outline.style) == LoadState::Loading);
+        if outline.enabled && !loading && !styles.contains(&outline.style) {
+            warnings.warn_once(
+                &OutlineError::MissingStyle(outline.style.id()),
+                "skipping outlines for cameras using it",
+            );
+        }
+    }
+
     let mut batches = Vec::with_capacity(*previous_outline_len);
     batches.extend(
         cam_outline_query
             .iter()
-            .filter_map(|(entity, outline)| outline.enabled.then(|| (entity, (outline.clone(),)))),
+            .filter(|(_, outline, _)| outline.enabled)
+            .map(|(entity, outline, settings)| {
+                // Settings on the camera take precedence over the resource.
+                let settings = settings.unwrap_or(&default_settings).clone();
+                (entity, (outline.clone(), settings))
+            }),
     );
     *previous_outline_len = batches.len();
     commands.insert_or_spawn_batch(batches);
 }
 
+type CameraOutlineQuery = (
+    Entity,
+    &'static CameraOutline,
+    Option<&'static OutlineSettings>,
+);
+type OutlineQuery = (
+    Entity,
+    &'static ComputedVisibility,
+    &'static GlobalTransform,
+    Option<&'static Handle<Mesh>>,
+    Option<&'static Aabb>,
+    Option<&'static OutlineProxy>,
+    Option<&'static Outline>,
+    Option<&'static InheritedOutline>,
+    Option<&'static RenderLayers>,
+);
+type OutlineFilter = (
+    Or<(With<Handle<Mesh>>, With<OutlineProxy>)>,
+    Or<(With<Outline>, With<InheritedOutline>)>,
+);
+
+#[allow(clippy::too_many_arguments)]
+fn extract_outlines(
+    mut commands: Commands,
+    mut previous_outline_len: Local<usize>,
+    mut previous_uniform_len: Local<usize>,
+    mut overscan: Local<HashSet<Entity>>,
+    mut object_indices: Local<ObjectIndices>,
+    warnings: Res<OutlineWarnings>,
+    outline_query: Extract<Query<OutlineQuery, OutlineFilter>>,
+    overscan_query: Extract<Query<&overscan::OverscanVisibleEntities>>,
+    fade_query: Extract<Query<&OutlineFade>>,
+    proxy_bounds: Extract<Res<OutlineProxyBounds>>,
+) {
+    // Entities culled from every view are still drawn in the guard bands of
+    // views using overscan.
+    overscan.clear();
+    overscan.extend(
+        overscan_query
+            .iter()
+            .flat_map(|overscan| overscan.entities.iter().copied()),
+    );
+
+    object_indices.0.clear();
+    let mut outlines = Vec::with_capacity(*previous_outline_len);
+    // Entities outlined only by a proxy, or culled from every view, have no
+    // mesh uniform of their own.
+    let mut mesh_uniforms = Vec::with_capacity(*previous_uniform_len);
+    outlines.extend(outline_query.iter().filter_map(
+        |(entity, visibility, transform, mesh, aabb, proxy, outline, inherited, layers)| {
+            let (source, enabled) = outline_source(entity, outline, inherited)?;
+
+            // Outlines fading out are still drawn until fully transparent.
+            let alpha = match fade_query.get(source) {
+                Ok(fade) => fade.alpha(),
+                Err(_) if enabled => 1.0,
+                Err(_) => 0.0,
+            };
+
+            let visible = visibility.is_visible();
+            if alpha <= 0.0 || !(visible || overscan.contains(&entity)) {
+                return None;
+            }
+
+            let (mask_mesh, aabb) = match (proxy, mesh) {
+                (Some(proxy), _) => (proxy.0.clone_weak(), proxy_bounds.get(&proxy.0)),
+                (None, Some(mesh)) => (mesh.clone_weak(), aabb.copied()),
+                (None, None) => return None,
+            };
+            if mesh.is_none() || !visible {
+                let transform = transform.compute_matrix();
+                let uniform = MeshUniform {
+                    transform,
+                    inverse_transpose_model: transform.inverse().transpose(),
+                    // The mask pass doesn't read the mesh flags.
+                    flags: 0,
+                };
+                mesh_uniforms.push((entity, uniform));
+            }
+
+            let extracted = ExtractedOutline {
+                layers: layers.copied().unwrap_or_default(),
+                mesh: mask_mesh,
+                aabb,
+            };
+            let uniform = OutlineMeshUniform::new(object_indices.get(source), alpha);
+            Some((entity, (extracted, uniform)))
+        },
+    ));
+
+    let objects = object_indices.0.len();
+    let max = OutlineMeshUniform::MAX_ID as usize - 1;
+    if objects > max {
+        warnings.warn_once(
+            &OutlineError::TooManyObjects { objects, max },
+            "objects sharing an ID have no contour between them",
+        );
+    }
+    *previous_outline_len = outlines.len();
+    *previous_uniform_len = mesh_uniforms.len();
+    commands.insert_or_spawn_batch(outlines);
+    commands.insert_or_spawn_batch(mesh_uniforms);
+}
+
+// Returns the entity whose outline applies to `entity`, and whether it is
+// enabled. An outline on the entity itself takes precedence over an inherited
+// one.
+fn outline_source(
+    entity: Entity,
+    outline: Option<&Outline>,
+    inherited: Option<&InheritedOutline>,
+) -> Option<(Entity, bool)> {
+    match (outline, inherited) {
+        (Some(outline), _) => Some((entity, outline.enabled)),
+        (None, Some(inherited)) => Some((inherited.source, inherited.enabled)),
+        (None, None) => None,
+    }
+}
+
+// Indices of the outlined objects of a frame, shared by all the meshes of an
+// object.
+#[derive(Default)]
+struct ObjectIndices(HashMap<Entity, usize>);
+
+impl ObjectIndices {
+    fn get(&mut self, source: Entity) -> usize {
+        let next = self.0.len();
+        *self.0.entry(source).or_insert(next)
+    }
+}
+
 fn extract_mask_camera_phase(
     mut commands: Commands,
     cameras: Extract<Query<Entity, (With<Camera3d>, With<CameraOutline>)>>,
 ) {
     for entity in cameras.iter() {
-        commands
-            .get_or_spawn(entity)
-            .insert(RenderPhase::<MeshMask>::default());
+        commands.get_or_spawn(entity).insert((
+            RenderPhase::<MeshMask>::default(),
+            RenderPhase::<hull::HullOutline>::default(),
+        ));
     }
 }
 
+type MaskViewQuery = (
+    &'static ExtractedView,
+    &'static mut VisibleEntities,
+    &'static mut RenderPhase<MeshMask>,
+    Option<&'static CameraOutline>,
+    Option<&'static OutlineSettings>,
+    Option<&'static overscan::OverscanVisibleEntities>,
+);
+
+#[allow(clippy::too_many_arguments)]
 fn queue_mesh_masks(
     mesh_mask_draw_functions: Res<DrawFunctions<MeshMask>>,
     mesh_mask_pipeline: Res<MeshMaskPipeline>,
     mut pipelines: ResMut<SpecializedMeshPipelines<MeshMaskPipeline>>,
     mut pipeline_cache: ResMut<PipelineCache>,
     render_meshes: Res<RenderAssets<Mesh>>,
-    outline_meshes: Query<(Entity, &Handle<Mesh>, &MeshUniform)>,
-    mut views: Query<(
-        &ExtractedView,
-        &mut VisibleEntities,
-        &mut RenderPhase<MeshMask>,
-    )>,
+    outline_meshes: Query<(Entity, &MeshUniform, &ExtractedOutline)>,
+    mut views: Query<MaskViewQuery>,
 ) {
     let draw_outline = mesh_mask_draw_functions
         .read()
         .get_id::<DrawMeshMask>()
         .unwrap();
 
-    for (view, visible_entities, mut mesh_mask_phase) in views.iter_mut() {
+    for (view, visible_entities, mut mesh_mask_phase, camera_outline, settings, overscan) in
+        views.iter_mut()
+    {
+        // Settings are only extracted for cameras with an enabled outline, and
+        // hull outlines don't use the mask.
+        let Some(settings) = settings else {
+            continue;
+        };
+        if settings.method != OutlineMethod::JumpFlood {
+            continue;
+        }
+        let outline_layers = camera_outline.map(|outline| outline.layers);
+
         let view_matrix = view.transform.compute_matrix();
         let inv_view_row_2 = view_matrix.inverse().row(2);
 
-        for visible_entity in visible_entities.entities.iter().copied() {
-            let (entity, mesh_handle, mesh_uniform) = match outline_meshes.get(visible_entity) {
+        let overscan = overscan.map(|overscan| &overscan.entities[..]);
+        for visible_entity in visible_entities
+            .entities
+            .iter()
+            .chain(overscan.unwrap_or_default())
+            .copied()
+        {
+            let (entity, mesh_uniform, outline) = match outline_meshes.get(visible_entity) {
                 Ok(m) => m,
                 Err(_) => continue,
             };
 
-            let mesh = match render_meshes.get(mesh_handle) {
+            if let Some(layers) = outline_layers {
+                if !layers.intersects(&outline.layers) {
+                    continue;
+                }
+            }
+
+            let mesh = match render_meshes.get(&outline.mesh) {
                 Some(m) => m,
                 None => continue,
             };
 
-            let key = MeshPipelineKey::from_primitive_topology(mesh.primitive_topology);
+            let key = MeshMaskPipelineKey {
+                mesh: MeshPipelineKey::from_primitive_topology(mesh.primitive_topology),
+                separate_objects: settings.separate_objects,
+                creases: settings.creases,
+                samples: settings.mask_samples(),
+            };
 
             let pipeline = pipelines
                 .specialize(&mut pipeline_cache, &mesh_mask_pipeline, key, &mesh.layout)
@@ -350,3 +1312,93 @@ fn queue_mesh_masks(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        asset::AssetPlugin,
+        hierarchy::BuildWorldChildren,
+        prelude::{default, shape, MinimalPlugins, Vec3},
+    };
+
+    use super::*;
+
+    #[test]
+    fn invalid_pattern_sizes_are_named_after_the_pattern() {
+        let style = |pattern| OutlineStyle {
+            fill: Some(OutlineFill {
+                pattern,
+                ..default()
+            }),
+            ..default()
+        };
+        let field = |style: OutlineStyle| match style.validate() {
+            Err(OutlineError::InvalidStyleValue { field, .. }) => field,
+            result => panic!("expected an invalid value, got {result:?}"),
+        };
+
+        let stripes = OutlineFillPattern::Stripes {
+            spacing: 4.0,
+            width: -1.0,
+        };
+        let dots = OutlineFillPattern::Dots {
+            spacing: 4.0,
+            radius: -1.0,
+        };
+        assert_eq!(field(style(stripes)), "fill.pattern.width");
+        assert_eq!(field(style(dots)), "fill.pattern.radius");
+    }
+
+    #[test]
+    fn proxy_bounds_follow_their_mesh() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin::default())
+            .add_asset::<Mesh>()
+            .init_resource::<OutlineProxyBounds>()
+            .add_system(update_proxy_bounds);
+
+        let cube = |size| Mesh::from(shape::Cube { size });
+        let mesh = app.world.resource_mut::<Assets<Mesh>>().add(cube(2.0));
+        app.world.spawn(OutlineProxy(mesh.clone()));
+        app.update();
+
+        let half_extents = |app: &App| {
+            let bounds = app.world.resource::<OutlineProxyBounds>();
+            Vec3::from(bounds.get(&mesh).unwrap().half_extents)
+        };
+        assert_eq!(half_extents(&app), Vec3::ONE);
+
+        app.world
+            .resource_mut::<Assets<Mesh>>()
+            .set_untracked(&mesh, cube(4.0));
+        app.update();
+        app.update();
+        assert_eq!(half_extents(&app), Vec3::splat(2.0));
+    }
+
+    #[test]
+    fn propagated_hierarchies_are_one_object() {
+        let mut app = App::new();
+        app.add_system(hierarchy::propagate_outlines);
+
+        let parts: Vec<_> = (0..3).map(|_| app.world.spawn_empty().id()).collect();
+        app.world
+            .spawn((Outline::default(), OutlinePropagation::Descendants))
+            .push_children(&parts);
+        let other = app.world.spawn(Outline::default()).id();
+        app.update();
+
+        let mut indices = ObjectIndices::default();
+        let mut index = |entity| {
+            let outline = app.world.get::<Outline>(entity);
+            let inherited = app.world.get::<InheritedOutline>(entity);
+            let (source, _) = outline_source(entity, outline, inherited).unwrap();
+            indices.get(source)
+        };
+
+        let part_indices: HashSet<_> = parts.iter().map(|&part| index(part)).collect();
+        assert_eq!(part_indices.len(), 1);
+        assert!(!part_indices.contains(&index(other)));
+    }
+}
@@ -0,0 +1,85 @@
+use bevy::{
+    asset::{AssetLoader, Error, LoadContext, LoadedAsset},
+    utils::BoxedFuture,
+};
+
+use crate::OutlineStyle;
+
+/// Asset loader for [`OutlineStyle`]s stored as RON.
+///
+/// Files must use the `.outline.ron` extension. Any field left out takes its
+/// value from [`OutlineStyle::default`]:
+///
+/// ```ron
+/// (
+///     color: Rgba(red: 1.0, green: 0.5, blue: 0.0, alpha: 1.0),
+///     width: 16.0,
+/// )
+/// ```
+#[derive(Default)]
+pub struct OutlineStyleLoader;
+
+impl AssetLoader for OutlineStyleLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let style = parse_style(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(style));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["outline.ron"]
+    }
+}
+
+fn parse_style(bytes: &[u8]) -> Result<OutlineStyle, ron::error::SpannedError> {
+    ron::de::from_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Color;
+
+    use super::*;
+    use crate::OutlineFill;
+
+    #[test]
+    fn omitted_fields_take_defaults() {
+        let style = parse_style(
+            br#"(
+                color: Rgba(red: 1.0, green: 0.5, blue: 0.0, alpha: 1.0),
+                width: 16.0,
+                fill: Some((
+                    color: Rgba(red: 0.0, green: 0.0, blue: 1.0, alpha: 0.5),
+                )),
+            )"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            style,
+            OutlineStyle {
+                color: Color::rgba(1.0, 0.5, 0.0, 1.0),
+                width: 16.0,
+                fill: Some(OutlineFill {
+                    color: Color::rgba(0.0, 0.0, 1.0, 0.5),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn malformed_input_is_rejected() {
+        let err = parse_style(b"(width: \"wide\")").unwrap_err();
+        assert_eq!(err.position.line, 1);
+
+        assert!(parse_style(b"(width: 16.0").is_err());
+    }
+}
@@ -1,65 +1,142 @@
 use bevy::{
+    ecs::system::{
+        lifetimeless::{Read, SRes},
+        SystemParamItem,
+    },
     pbr::{MeshPipeline, MeshPipelineKey},
     prelude::*,
     render::{
-        mesh::MeshVertexBufferLayout,
+        extract_component::{ComponentUniforms, DynamicUniformIndex},
+        mesh::{GpuBufferInfo, MeshVertexBufferLayout},
+        render_asset::RenderAssets,
         render_graph::{Node, RenderGraphContext, SlotInfo, SlotType},
-        render_phase::RenderPhase,
+        render_phase::{
+            PhaseItem, RenderCommand, RenderCommandResult, RenderPhase, TrackedRenderPass,
+        },
         render_resource::{
+            BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+            BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingType, BufferBindingType,
             ColorTargetState, ColorWrites, FragmentState, LoadOp, MultisampleState, Operations,
-            RenderPassColorAttachment, RenderPassDescriptor, RenderPipelineDescriptor,
-            SpecializedMeshPipeline, SpecializedMeshPipelineError, TextureFormat,
+            RenderPassColorAttachment, RenderPassDepthStencilAttachment, RenderPassDescriptor,
+            RenderPipelineDescriptor, ShaderStages, ShaderType, SpecializedMeshPipeline,
+            SpecializedMeshPipelineError,
         },
-        renderer::RenderContext,
+        renderer::{RenderContext, RenderDevice},
+        texture::CachedTexture,
     },
 };
 
-use crate::{resources::OutlineResources, MeshMask, MASK_SHADER_HANDLE};
+use crate::{
+    resources::{OutlineResources, ViewOutlineTextures},
+    temporal::ReuseOutlineFlood,
+    timings::{time_pass, OutlinePass},
+    ExtractedOutline, MeshMask, OutlineMeshUniform, MASK_SHADER_HANDLE, MASK_TEXTURE_FORMAT,
+    NORMAL_DEPTH_TEXTURE_FORMAT, OBJECT_ID_TEXTURE_FORMAT,
+};
 
 #[derive(Resource)]
 pub struct MeshMaskPipeline {
     mesh_pipeline: MeshPipeline,
+    pub(crate) outline_mesh_layout: BindGroupLayout,
+    dimensions_layout: BindGroupLayout,
 }
 
 impl FromWorld for MeshMaskPipeline {
     fn from_world(world: &mut World) -> Self {
         let mesh_pipeline = world.get_resource::<MeshPipeline>().unwrap().clone();
+        let dimensions_layout = world
+            .resource::<OutlineResources>()
+            .dimensions_bind_group_layout
+            .clone();
 
-        MeshMaskPipeline { mesh_pipeline }
+        let device = world.resource::<RenderDevice>();
+        let outline_mesh_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("outline_mesh_bind_group_layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: Some(OutlineMeshUniform::min_size()),
+                },
+                count: None,
+            }],
+        });
+
+        MeshMaskPipeline {
+            mesh_pipeline,
+            outline_mesh_layout,
+            dimensions_layout,
+        }
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MeshMaskPipelineKey {
+    pub mesh: MeshPipelineKey,
+    pub separate_objects: bool,
+    pub creases: bool,
+    pub samples: u32,
+}
+
 impl SpecializedMeshPipeline for MeshMaskPipeline {
-    type Key = MeshPipelineKey;
+    type Key = MeshMaskPipelineKey;
 
     fn specialize(
         &self,
         key: Self::Key,
         layout: &MeshVertexBufferLayout,
     ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
-        let mut desc = self.mesh_pipeline.specialize(key, layout)?;
+        let mut desc = self.mesh_pipeline.specialize(key.mesh, layout)?;
 
         desc.layout = vec![
             self.mesh_pipeline.view_layout_multisampled.clone(),
             self.mesh_pipeline.mesh_layout.clone(),
+            self.outline_mesh_layout.clone(),
+            self.dimensions_layout.clone(),
         ];
 
         desc.vertex.shader = MASK_SHADER_HANDLE.typed::<Shader>();
 
+        // Targets keep their location whichever are written.
+        let target = |format| ColorTargetState {
+            format,
+            blend: None,
+            write_mask: ColorWrites::ALL,
+        };
+        let mut shader_defs = vec![];
+        let mut targets = vec![Some(target(MASK_TEXTURE_FORMAT)), None, None];
+        if key.separate_objects {
+            shader_defs.push("SEPARATE_OBJECTS".into());
+            targets[1] = Some(target(OBJECT_ID_TEXTURE_FORMAT));
+        }
+        if key.creases {
+            shader_defs.push("CREASES".into());
+            desc.vertex.shader_defs.push("CREASES".into());
+            targets[2] = Some(target(NORMAL_DEPTH_TEXTURE_FORMAT));
+
+            // Meshes without normals still write to the normal and depth
+            // target, which the render pass has either way. The mesh pipeline
+            // already defines `VERTEX_NORMALS` for the vertex shader.
+            if layout.contains(Mesh::ATTRIBUTE_NORMAL) {
+                shader_defs.push("VERTEX_NORMALS".into());
+            }
+        }
+
         desc.fragment = Some(FragmentState {
             shader: MASK_SHADER_HANDLE.typed::<Shader>(),
-            shader_defs: vec![],
+            shader_defs,
             entry_point: "fragment".into(),
-            targets: vec![Some(ColorTargetState {
-                format: TextureFormat::R8Unorm,
-                blend: None,
-                write_mask: ColorWrites::ALL,
-            })],
+            targets,
         });
-        desc.depth_stencil = None;
+        // Normals and depths are those of the nearest surface.
+        if !key.creases {
+            desc.depth_stencil = None;
+        }
 
         desc.multisample = MultisampleState {
-            count: 4,
+            count: key.samples,
             mask: !0,
             alpha_to_coverage_enabled: false,
         };
@@ -69,9 +146,119 @@ impl SpecializedMeshPipeline for MeshMaskPipeline {
     }
 }
 
+#[derive(Resource)]
+pub struct OutlineMeshBindGroup {
+    value: BindGroup,
+}
+
+pub fn queue_outline_mesh_bind_group(
+    mut commands: Commands,
+    mask_pipeline: Res<MeshMaskPipeline>,
+    device: Res<RenderDevice>,
+    outline_mesh_uniforms: Res<ComponentUniforms<OutlineMeshUniform>>,
+) {
+    if let Some(binding) = outline_mesh_uniforms.uniforms().binding() {
+        commands.insert_resource(OutlineMeshBindGroup {
+            value: device.create_bind_group(&BindGroupDescriptor {
+                label: Some("outline_mesh_bind_group"),
+                layout: &mask_pipeline.outline_mesh_layout,
+                entries: &[BindGroupEntry {
+                    binding: 0,
+                    resource: binding,
+                }],
+            }),
+        });
+    }
+}
+
+/// Render command for binding the per-mesh outline uniform.
+pub struct SetOutlineMeshBindGroup<const I: usize>;
+
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetOutlineMeshBindGroup<I> {
+    type Param = SRes<OutlineMeshBindGroup>;
+    type ViewWorldQuery = ();
+    type ItemWorldQuery = Read<DynamicUniformIndex<OutlineMeshUniform>>;
+
+    #[inline]
+    fn render<'w>(
+        _item: &P,
+        _view: (),
+        index: &'w DynamicUniformIndex<OutlineMeshUniform>,
+        bind_group: SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        pass.set_bind_group(I, &bind_group.into_inner().value, &[index.index()]);
+        RenderCommandResult::Success
+    }
+}
+
+/// Render command for binding the dimensions of the view's outline textures,
+/// which the mask shrinks its projection by to leave room for the overscan.
+pub(crate) struct SetDimensionsBindGroup<const I: usize>;
+
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetDimensionsBindGroup<I> {
+    type Param = ();
+    type ViewWorldQuery = Read<ViewOutlineTextures>;
+    type ItemWorldQuery = ();
+
+    #[inline]
+    fn render<'w>(
+        _item: &P,
+        textures: &'w ViewOutlineTextures,
+        _entity: (),
+        _param: SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        pass.set_bind_group(I, &textures.dimensions_bind_group, &[]);
+        RenderCommandResult::Success
+    }
+}
+
+/// Render command for drawing the mesh outlining an entity, which is its
+/// [`OutlineProxy`](crate::OutlineProxy) if it has one.
+pub(crate) struct DrawOutlineMesh;
+
+impl<P: PhaseItem> RenderCommand<P> for DrawOutlineMesh {
+    type Param = SRes<RenderAssets<Mesh>>;
+    type ViewWorldQuery = ();
+    type ItemWorldQuery = Read<ExtractedOutline>;
+
+    #[inline]
+    fn render<'w>(
+        _item: &P,
+        _view: (),
+        outline: &'w ExtractedOutline,
+        meshes: SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(gpu_mesh) = meshes.into_inner().get(&outline.mesh) else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
+        match &gpu_mesh.buffer_info {
+            GpuBufferInfo::Indexed {
+                buffer,
+                index_format,
+                count,
+            } => {
+                pass.set_index_buffer(buffer.slice(..), 0, *index_format);
+                pass.draw_indexed(0..*count, 0, 0..1);
+            }
+            GpuBufferInfo::NonIndexed { vertex_count } => {
+                pass.draw(0..*vertex_count, 0..1);
+            }
+        }
+
+        RenderCommandResult::Success
+    }
+}
+
+type MaskViewQuery = (&'static RenderPhase<MeshMask>, &'static ViewOutlineTextures);
+
 /// Render graph node for producing stencils from meshes.
 pub struct MeshMaskNode {
-    query: QueryState<&'static RenderPhase<MeshMask>>,
+    query: QueryState<MaskViewQuery>,
 }
 
 impl MeshMaskNode {
@@ -111,31 +298,78 @@ impl Node for MeshMaskNode {
         world: &World,
     ) -> Result<(), bevy::render::render_graph::NodeRunError> {
         let res = world.get_resource::<OutlineResources>().unwrap();
-
-        graph
-            .set_output(Self::OUT_MASK, res.mask_multisample.default_view.clone())
-            .unwrap();
-
-        let view_entity = graph.get_input_entity(Self::IN_VIEW).unwrap();
-        let Ok(stencil_phase) = self.query.get_manual(world, view_entity) else {
+        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
+        let Ok((stencil_phase, textures)) = self.query.get_manual(world, view_entity) else {
+            graph.set_output(Self::OUT_MASK, res.placeholder.default_view.clone())?;
             return Ok(());
         };
 
-        let mut tracked_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
-            label: Some("outline_stencil_render_pass"),
-            color_attachments: &[Some(RenderPassColorAttachment {
-                view: &res.mask_multisample.default_view,
-                resolve_target: Some(&res.mask_output.default_view),
-                ops: Operations {
-                    load: LoadOp::Clear(Color::BLACK.into()),
-                    store: true,
-                },
-            })],
-            depth_stencil_attachment: None,
-        });
+        let mask_target = textures.mask_multisample.as_ref();
+        let mask_target = mask_target.unwrap_or(&textures.mask_output);
+        graph.set_output(Self::OUT_MASK, mask_target.default_view.clone())?;
+        // The mask is kept from the previous frame.
+        if world.get::<ReuseOutlineFlood>(view_entity).is_some() {
+            return Ok(());
+        }
 
-        stencil_phase.render(&mut tracked_pass, world, view_entity);
+        // Object IDs are only written when separating objects, and normals and
+        // depths when drawing creases.
+        let color_attachments = [
+            color_attachment(textures.mask_multisample.as_ref(), &textures.mask_output),
+            textures
+                .object_id_output
+                .as_ref()
+                .and_then(|output| color_attachment(None, output)),
+            textures.normal_depth_output.as_ref().and_then(|output| {
+                color_attachment(textures.normal_depth_multisample.as_ref(), output)
+            }),
+        ];
+        let depth_stencil_attachment =
+            textures
+                .mask_depth
+                .as_ref()
+                .map(|depth| RenderPassDepthStencilAttachment {
+                    view: &depth.default_view,
+                    // Reverse-Z, like the main passes.
+                    depth_ops: Some(Operations {
+                        load: LoadOp::Clear(0.0),
+                        store: false,
+                    }),
+                    stencil_ops: None,
+                });
+
+        time_pass(
+            world,
+            render_context,
+            view_entity,
+            OutlinePass::Mask,
+            |render_context| {
+                let mut tracked_pass =
+                    render_context.begin_tracked_render_pass(RenderPassDescriptor {
+                        label: Some("outline_stencil_render_pass"),
+                        color_attachments: &color_attachments,
+                        depth_stencil_attachment,
+                    });
+
+                stencil_phase.render(&mut tracked_pass, world, view_entity);
+            },
+        );
 
         Ok(())
     }
 }
+
+// Multisampled targets are resolved to the output.
+fn color_attachment<'a>(
+    multisample: Option<&'a CachedTexture>,
+    output: &'a CachedTexture,
+) -> Option<RenderPassColorAttachment<'a>> {
+    Some(RenderPassColorAttachment {
+        view: &multisample.unwrap_or(output).default_view,
+        resolve_target: multisample.map(|_| &*output.default_view),
+        ops: Operations {
+            load: LoadOp::Clear(Color::BLACK.into()),
+            store: true,
+        },
+    })
+}
@@ -1,24 +1,36 @@
 use bevy::{
+    ecs::system::{
+        lifetimeless::{Read, SRes},
+        SystemParamItem,
+    },
     prelude::*,
     render::{
+        camera::ExtractedCamera,
         render_asset::RenderAssets,
         render_graph::{Node, NodeRunError, RenderGraphContext, SlotInfo, SlotType},
+        render_phase::{PhaseItem, RenderCommand, RenderCommandResult, TrackedRenderPass},
         render_resource::{
-            BindGroup, BindGroupLayout, BlendComponent, BlendFactor, BlendOperation, BlendState,
-            CachedRenderPipelineId, ColorTargetState, ColorWrites, FragmentState, LoadOp,
+            BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BlendComponent,
+            BlendFactor, BlendOperation, BlendState, BufferId, CachedRenderPipelineId,
+            ColorTargetState, ColorWrites, DynamicUniformBuffer, FragmentState, LoadOp,
             MultisampleState, Operations, PipelineCache, RenderPassColorAttachment,
             RenderPassDescriptor, RenderPipelineDescriptor, ShaderType, SpecializedRenderPipeline,
             SpecializedRenderPipelines, TextureFormat, TextureSampleType, TextureUsages,
-            UniformBuffer, VertexState,
+            VertexState,
         },
-        renderer::RenderContext,
-        view::ViewTarget,
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        texture::BevyDefault,
+        view::{ExtractedView, ViewTarget},
     },
 };
 
 use crate::{
-    resources::{self, OutlineResources},
-    CameraOutline, OutlineStyle, FULLSCREEN_PRIMITIVE_STATE, OUTLINE_SHADER_HANDLE,
+    error::{OutlineError, OutlineWarnings},
+    resources::{OutlineResources, ViewOutlineTextures},
+    timings::{time_pass, OutlinePass},
+    transition::ExtractedStyleBlend,
+    CameraOutline, OutlineFillPattern, OutlineSettings, OutlineStyle, FULLSCREEN_PRIMITIVE_STATE,
+    OUTLINE_SHADER_HANDLE,
 };
 
 #[derive(Clone, Debug, Default, PartialEq, ShaderType)]
@@ -27,39 +39,347 @@ pub struct OutlineParams {
     pub(crate) color: Vec4,
     // Outline weight in pixels.
     pub(crate) weight: f32,
+    // Weight in pixels of lines between adjacent objects.
+    pub(crate) contour_weight: f32,
+    // Distance in pixels over which the edges of the outline fade out.
+    pub(crate) softness: f32,
+    // Shadow color, transparent without a shadow.
+    pub(crate) shadow_color: Vec4,
+    // Shadow offset in pixels.
+    pub(crate) shadow_offset: Vec2,
+    // Distance in pixels over which the shadow fades out.
+    pub(crate) shadow_softness: f32,
+    // Fill color, transparent without a fill.
+    pub(crate) fill_color: Vec4,
+    // Distance in pixels over which the fill fades out from the edge, or zero.
+    pub(crate) fill_inner_distance: f32,
+    // Fill pattern: 0 for solid, 1 for stripes, 2 for dots.
+    pub(crate) fill_pattern: u32,
+    // Pattern repeat distance in pixels.
+    pub(crate) fill_pattern_spacing: f32,
+    // Stripe width or dot radius in pixels.
+    pub(crate) fill_pattern_size: f32,
+    // Crease line color, transparent without creases.
+    pub(crate) crease_color: Vec4,
+    // Crease line weight in pixels.
+    pub(crate) crease_weight: f32,
+    // Change in normal and relative change in depth marking a crease.
+    pub(crate) crease_normal_threshold: f32,
+    pub(crate) crease_depth_threshold: f32,
 }
 
 impl OutlineParams {
-    pub fn new(color: Color, weight: f32) -> OutlineParams {
-        let color: Vec4 = color.as_rgba_f32().into();
+    pub fn from_style(style: &OutlineStyle) -> OutlineParams {
+        let color: Vec4 = style.color.as_rgba_f32().into();
+        let (shadow_color, shadow_offset, shadow_softness) = match &style.shadow {
+            Some(shadow) => (
+                shadow.color.as_rgba_f32().into(),
+                shadow.offset,
+                shadow.softness,
+            ),
+            None => (Vec4::ZERO, Vec2::ZERO, 0.0),
+        };
+        let (fill_color, fill_inner_distance, pattern) = match &style.fill {
+            Some(fill) => (
+                fill.color.as_rgba_f32().into(),
+                fill.inner_distance,
+                fill.pattern,
+            ),
+            None => (Vec4::ZERO, 0.0, OutlineFillPattern::Solid),
+        };
+        let (crease_color, crease_weight, crease_normal_threshold, crease_depth_threshold) =
+            match &style.creases {
+                Some(creases) => (
+                    creases.color.as_rgba_f32().into(),
+                    creases.width,
+                    creases.normal_threshold,
+                    creases.depth_threshold,
+                ),
+                None => (Vec4::ZERO, 0.0, 0.0, 0.0),
+            };
+        let (fill_pattern, fill_pattern_spacing, fill_pattern_size) = match pattern {
+            OutlineFillPattern::Solid => (0, 0.0, 0.0),
+            OutlineFillPattern::Stripes { spacing, width } => (1, spacing, width),
+            OutlineFillPattern::Dots { spacing, radius } => (2, spacing, radius),
+        };
+
+        OutlineParams {
+            color,
+            weight: style.width,
+            contour_weight: style.contour_width,
+            softness: style.softness,
+            shadow_color,
+            shadow_offset,
+            shadow_softness,
+            fill_color,
+            fill_inner_distance,
+            fill_pattern,
+            fill_pattern_spacing,
+            fill_pattern_size,
+            crease_color,
+            crease_weight,
+            crease_normal_threshold,
+            crease_depth_threshold,
+        }
+    }
+
+    /// Linearly interpolates between `self` and `other`.
+    pub fn lerp(&self, other: &OutlineParams, t: f32) -> OutlineParams {
+        // Fill patterns switch halfway through.
+        let pattern = if t < 0.5 { self } else { other };
+
+        OutlineParams {
+            color: self.color.lerp(other.color, t),
+            weight: self.weight + (other.weight - self.weight) * t,
+            contour_weight: self.contour_weight + (other.contour_weight - self.contour_weight) * t,
+            softness: self.softness + (other.softness - self.softness) * t,
+            shadow_color: self.shadow_color.lerp(other.shadow_color, t),
+            shadow_offset: self.shadow_offset.lerp(other.shadow_offset, t),
+            shadow_softness: self.shadow_softness
+                + (other.shadow_softness - self.shadow_softness) * t,
+            fill_color: self.fill_color.lerp(other.fill_color, t),
+            fill_inner_distance: self.fill_inner_distance
+                + (other.fill_inner_distance - self.fill_inner_distance) * t,
+            fill_pattern: pattern.fill_pattern,
+            fill_pattern_spacing: pattern.fill_pattern_spacing,
+            fill_pattern_size: pattern.fill_pattern_size,
+            crease_color: self.crease_color.lerp(other.crease_color, t),
+            crease_weight: self.crease_weight + (other.crease_weight - self.crease_weight) * t,
+            crease_normal_threshold: self.crease_normal_threshold
+                + (other.crease_normal_threshold - self.crease_normal_threshold) * t,
+            crease_depth_threshold: self.crease_depth_threshold
+                + (other.crease_depth_threshold - self.crease_depth_threshold) * t,
+        }
+    }
+
+    /// Returns whether a shadow is drawn.
+    pub(crate) fn has_shadow(&self) -> bool {
+        self.shadow_color.w > 0.0
+    }
+
+    /// Returns whether the fill fades out from the edge, which requires the
+    /// distance from interior fragments to the edge.
+    pub(crate) fn needs_inner_distance(&self) -> bool {
+        self.fill_color.w > 0.0 && self.fill_inner_distance > 0.0
+    }
+
+    /// Returns the distance in pixels the jump flood must cover.
+    pub fn flood_distance(&self, separate_objects: bool) -> f32 {
+        let mut distance = self.weight;
+        if separate_objects {
+            distance = distance.max(self.contour_weight);
+        }
+        // Shadows sample the flood at an offset, so only their falloff needs
+        // to be covered.
+        if self.has_shadow() {
+            distance = distance.max(shadow_reach(self.shadow_softness));
+        }
+        if self.needs_inner_distance() {
+            distance = distance.max(self.fill_inner_distance);
+        }
+
+        distance
+    }
 
-        OutlineParams { color, weight }
+    /// Returns the greatest distance in pixels from the outlined objects at
+    /// which anything is drawn.
+    pub(crate) fn reach(&self) -> f32 {
+        let mut reach = self.weight;
+        if self.has_shadow() {
+            reach = reach.max(self.shadow_offset.length() + shadow_reach(self.shadow_softness));
+        }
+
+        reach
     }
+
+    /// Returns the width in pixels of the guard band needed around a view for
+    /// objects outside it to be outlined.
+    ///
+    /// The width is rounded up, so that animating the outline rarely resizes
+    /// the outline textures, and capped to keep them from growing unbounded.
+    pub(crate) fn overscan(&self) -> u32 {
+        const STEP: f32 = 8.0;
+        const MAX: f32 = 256.0;
+
+        ((self.reach() / STEP).ceil() * STEP).clamp(0.0, MAX) as u32
+    }
+}
+
+/// Returns the distance in pixels at which a shadow of the given softness
+/// fades out completely.
+pub(crate) fn shadow_reach(softness: f32) -> f32 {
+    softness.max(1.0)
 }
 
 pub struct GpuOutlineParams {
     pub(crate) params: OutlineParams,
-    pub(crate) _buffer: UniformBuffer<OutlineParams>,
-    pub(crate) bind_group: BindGroup,
+}
+
+impl GpuOutlineParams {
+    /// Returns the parameters of a view using the style, part way through a
+    /// transition from another style if there is one.
+    pub(crate) fn blended(&self, blend: Option<&ExtractedStyleBlend>) -> OutlineParams {
+        match blend {
+            Some(blend) => blend.from.lerp(&self.params, blend.t),
+            None => self.params.clone(),
+        }
+    }
+}
+
+/// Outline parameters for each view, after applying style transitions.
+#[derive(Default, Resource)]
+pub struct ViewOutlineUniforms {
+    uniforms: DynamicUniformBuffer<OutlineParams>,
+    // Only recreated when the uniform buffer is reallocated.
+    bind_group: Option<(BufferId, BindGroup)>,
+}
+
+#[derive(Component)]
+pub struct ViewOutlineParams {
+    pub(crate) params: OutlineParams,
+    offset: u32,
+    // Specialized for the format of the view target.
+    pipeline: CachedRenderPipelineId,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn prepare_view_outline_params(
+    mut commands: Commands,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+    res: Res<OutlineResources>,
+    styles: Res<RenderAssets<OutlineStyle>>,
+    warnings: Res<OutlineWarnings>,
+    pipeline_cache: Res<PipelineCache>,
+    outline_pipeline: Res<OutlinePipeline>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<OutlinePipeline>>,
+    mut view_uniforms: ResMut<ViewOutlineUniforms>,
+    views: Query<(
+        Entity,
+        &ExtractedView,
+        &CameraOutline,
+        &OutlineSettings,
+        Option<&ExtractedStyleBlend>,
+    )>,
+) {
+    let ViewOutlineUniforms {
+        uniforms,
+        bind_group,
+    } = &mut *view_uniforms;
+
+    uniforms.clear();
+    for (entity, view, outline, settings, blend) in views.iter() {
+        // Missing styles are reported when extracting the camera.
+        let Some(style) = styles.get(&outline.style) else {
+            continue;
+        };
+
+        let format = if view.hdr {
+            ViewTarget::TEXTURE_FORMAT_HDR
+        } else {
+            TextureFormat::bevy_default()
+        };
+        let Some(key) =
+            OutlinePipelineKey::new(format, settings.separate_objects, settings.creases)
+                .map(|key| key.with_multisampled_ids(settings.multisampled_ids()))
+        else {
+            warnings.warn_once(
+                &OutlineError::UnsupportedTargetFormat(format),
+                "skipping outlines for views using it",
+            );
+            continue;
+        };
+        let pipeline = pipelines.specialize(&pipeline_cache, &outline_pipeline, key);
+
+        let params = style.blended(blend);
+
+        let offset = uniforms.push(params.clone());
+        commands.entity(entity).insert(ViewOutlineParams {
+            params,
+            offset,
+            pipeline,
+        });
+    }
+    uniforms.write_buffer(&device, &queue);
+
+    let Some(buffer) = uniforms.buffer() else {
+        return;
+    };
+
+    if bind_group.as_ref().map(|(id, _)| *id) != Some(buffer.id()) {
+        let value = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("outline_params_bind_group"),
+            layout: &res.outline_params_bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: uniforms.binding().unwrap(),
+            }],
+        });
+        *bind_group = Some((buffer.id(), value));
+    }
+}
+
+/// Blending of outlines over the view target.
+pub(crate) const OUTLINE_BLEND: BlendState = BlendState {
+    color: BlendComponent {
+        src_factor: BlendFactor::SrcAlpha,
+        dst_factor: BlendFactor::OneMinusSrcAlpha,
+        operation: BlendOperation::Add,
+    },
+    alpha: BlendComponent {
+        src_factor: BlendFactor::One,
+        dst_factor: BlendFactor::Zero,
+        operation: BlendOperation::Add,
+    },
+};
+
+/// Render command for binding the outline parameters of the view.
+pub(crate) struct SetOutlineParamsBindGroup<const I: usize>;
+
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetOutlineParamsBindGroup<I> {
+    type Param = SRes<ViewOutlineUniforms>;
+    type ViewWorldQuery = Read<ViewOutlineParams>;
+    type ItemWorldQuery = ();
+
+    #[inline]
+    fn render<'w>(
+        _item: &P,
+        params: &'w ViewOutlineParams,
+        _entity: (),
+        view_uniforms: SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some((_, bind_group)) = &view_uniforms.into_inner().bind_group else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_bind_group(I, bind_group, &[params.offset]);
+        RenderCommandResult::Success
+    }
 }
 
 #[derive(Clone, Debug, Resource)]
 pub struct OutlinePipeline {
     dimensions_layout: BindGroupLayout,
     input_layout: BindGroupLayout,
+    // The above, reading multisampled object IDs.
+    multisampled_ids_input_layout: BindGroupLayout,
     params_layout: BindGroupLayout,
 }
 
 impl FromWorld for OutlinePipeline {
     fn from_world(world: &mut World) -> Self {
-        let res = world.get_resource::<resources::OutlineResources>().unwrap();
+        let res = world.get_resource::<OutlineResources>().unwrap();
         let dimensions_layout = res.dimensions_bind_group_layout.clone();
         let input_layout = res.outline_src_bind_group_layout.clone();
+        let multisampled_ids_input_layout =
+            res.outline_src_multisampled_ids_bind_group_layout.clone();
         let params_layout = res.outline_params_bind_group_layout.clone();
 
         OutlinePipeline {
             dimensions_layout,
             input_layout,
+            multisampled_ids_input_layout,
             params_layout,
         }
     }
@@ -68,51 +388,69 @@ impl FromWorld for OutlinePipeline {
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct OutlinePipelineKey {
     format: TextureFormat,
+    separate_objects: bool,
+    creases: bool,
+    multisampled_ids: bool,
 }
 
 impl OutlinePipelineKey {
-    pub fn new(format: TextureFormat) -> Option<OutlinePipelineKey> {
-        let info = format.describe();
+    pub fn new(
+        format: TextureFormat,
+        separate_objects: bool,
+        creases: bool,
+    ) -> Option<OutlinePipelineKey> {
+        is_color_target_format(format).then_some(OutlinePipelineKey {
+            format,
+            separate_objects,
+            creases,
+            multisampled_ids: false,
+        })
+    }
 
-        if info.sample_type == TextureSampleType::Depth {
-            // Can't use this format as a color attachment.
-            return None;
-        }
+    /// Reads object IDs from a multisampled texture, when separating objects.
+    pub fn with_multisampled_ids(mut self, multisampled_ids: bool) -> OutlinePipelineKey {
+        self.multisampled_ids = self.separate_objects && multisampled_ids;
+        self
+    }
+}
 
-        if info
-            .guaranteed_format_features
-            .allowed_usages
-            .contains(TextureUsages::RENDER_ATTACHMENT)
-        {
-            Some(OutlinePipelineKey { format })
-        } else {
-            None
-        }
+/// Returns whether `format` can be used as a color attachment.
+pub(crate) fn is_color_target_format(format: TextureFormat) -> bool {
+    let info = format.describe();
+
+    if info.sample_type == TextureSampleType::Depth {
+        // Can't use this format as a color attachment.
+        return false;
     }
+
+    info.guaranteed_format_features
+        .allowed_usages
+        .contains(TextureUsages::RENDER_ATTACHMENT)
 }
 
 impl SpecializedRenderPipeline for OutlinePipeline {
     type Key = OutlinePipelineKey;
 
     fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
-        let blend = BlendState {
-            color: BlendComponent {
-                src_factor: BlendFactor::SrcAlpha,
-                dst_factor: BlendFactor::OneMinusSrcAlpha,
-                operation: BlendOperation::Add,
-            },
-            alpha: BlendComponent {
-                src_factor: BlendFactor::One,
-                dst_factor: BlendFactor::Zero,
-                operation: BlendOperation::Add,
-            },
+        let mut shader_defs = vec![];
+        if key.separate_objects {
+            shader_defs.push("SEPARATE_OBJECTS".into());
+        }
+        if key.creases {
+            shader_defs.push("CREASES".into());
+        }
+        let input_layout = if key.multisampled_ids {
+            shader_defs.push("MULTISAMPLED_IDS".into());
+            &self.multisampled_ids_input_layout
+        } else {
+            &self.input_layout
         };
 
         RenderPipelineDescriptor {
             label: Some("jfa_outline_pipeline".into()),
             layout: vec![
                 self.dimensions_layout.clone(),
-                self.input_layout.clone(),
+                input_layout.clone(),
                 self.params_layout.clone(),
             ],
             vertex: VertexState {
@@ -123,11 +461,11 @@ impl SpecializedRenderPipeline for OutlinePipeline {
             },
             fragment: Some(FragmentState {
                 shader: OUTLINE_SHADER_HANDLE.typed::<Shader>(),
-                shader_defs: vec![],
+                shader_defs,
                 entry_point: "fragment".into(),
                 targets: vec![Some(ColorTargetState {
                     format: key.format,
-                    blend: Some(blend),
+                    blend: Some(OUTLINE_BLEND),
                     write_mask: ColorWrites::ALL,
                 })],
             }),
@@ -143,30 +481,28 @@ impl SpecializedRenderPipeline for OutlinePipeline {
     }
 }
 
+type OutlineViewQuery = (
+    &'static ViewOutlineParams,
+    &'static ViewTarget,
+    &'static ViewOutlineTextures,
+    Option<&'static ExtractedCamera>,
+);
+
 pub struct OutlineNode {
-    pipeline_id: CachedRenderPipelineId,
-    query: QueryState<(&'static CameraOutline, &'static ViewTarget)>,
+    query: QueryState<OutlineViewQuery>,
 }
 
 impl OutlineNode {
     pub const IN_VIEW: &'static str = "in_view";
     pub const IN_JFA: &'static str = "in_jfa";
     pub const OUT_VIEW: &'static str = "out_view";
+}
 
-    pub fn new(world: &mut World, target_format: TextureFormat) -> OutlineNode {
-        let pipeline_id = world.resource_scope(|world, mut cache: Mut<PipelineCache>| {
-            let base = world.get_resource::<OutlinePipeline>().unwrap().clone();
-            let mut spec = world
-                .get_resource_mut::<SpecializedRenderPipelines<OutlinePipeline>>()
-                .unwrap();
-            let key =
-                OutlinePipelineKey::new(target_format).expect("invalid format for OutlineNode");
-            spec.specialize(&mut cache, &base, key)
-        });
-
-        let query = QueryState::new(world);
-
-        OutlineNode { pipeline_id, query }
+impl FromWorld for OutlineNode {
+    fn from_world(world: &mut World) -> Self {
+        OutlineNode {
+            query: QueryState::new(world),
+        }
     }
 }
 
@@ -204,38 +540,54 @@ impl Node for OutlineNode {
         let view_ent = graph.get_input_entity(Self::IN_VIEW)?;
         graph.set_output(Self::OUT_VIEW, view_ent)?;
 
-        let (outline, target) = self.query.get_manual(world, view_ent).unwrap();
-
-        let styles = world.resource::<RenderAssets<OutlineStyle>>();
-        let style = styles.get(&outline.style).unwrap();
+        let Ok((params, target, textures, camera)) = self.query.get_manual(world, view_ent) else {
+            return Ok(());
+        };
 
-        let res = world.get_resource::<OutlineResources>().unwrap();
+        let view_uniforms = world.resource::<ViewOutlineUniforms>();
+        let Some((_, params_bind_group)) = &view_uniforms.bind_group else {
+            return Ok(());
+        };
 
-        let pipelines = world.get_resource::<PipelineCache>().unwrap();
-        let pipeline = match pipelines.get_render_pipeline(self.pipeline_id) {
+        let pipelines = world.resource::<PipelineCache>();
+        let pipeline = match pipelines.get_render_pipeline(params.pipeline) {
             Some(p) => p,
             None => return Ok(()),
         };
 
-        let mut tracked_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
-            label: Some("jfa_outline"),
-            color_attachments: &[Some(RenderPassColorAttachment {
-                view: target.main_texture(),
-                resolve_target: None,
-                ops: Operations {
-                    load: LoadOp::Load,
-                    store: true,
-                },
-            })],
-            // TODO: support outlines being occluded by world geometry
-            depth_stencil_attachment: None,
-        });
+        time_pass(
+            world,
+            render_context,
+            view_ent,
+            OutlinePass::Outline,
+            |render_context| {
+                let mut tracked_pass =
+                    render_context.begin_tracked_render_pass(RenderPassDescriptor {
+                        label: Some("jfa_outline"),
+                        color_attachments: &[Some(RenderPassColorAttachment {
+                            view: target.main_texture(),
+                            resolve_target: None,
+                            ops: Operations {
+                                load: LoadOp::Load,
+                                store: true,
+                            },
+                        })],
+                        // TODO: support outlines being occluded by world geometry
+                        depth_stencil_attachment: None,
+                    });
 
-        tracked_pass.set_render_pipeline(pipeline);
-        tracked_pass.set_bind_group(0, &res.dimensions_bind_group, &[]);
-        tracked_pass.set_bind_group(1, &res.outline_src_bind_group, &[]);
-        tracked_pass.set_bind_group(2, &style.bind_group, &[]);
-        tracked_pass.draw(0..3, 0..1);
+                // The outline textures cover only the camera's viewport.
+                if let Some(viewport) = camera.and_then(|camera| camera.viewport.as_ref()) {
+                    tracked_pass.set_camera_viewport(viewport);
+                }
+
+                tracked_pass.set_render_pipeline(pipeline);
+                tracked_pass.set_bind_group(0, &textures.dimensions_bind_group, &[]);
+                tracked_pass.set_bind_group(1, &textures.outline_src_bind_group, &[]);
+                tracked_pass.set_bind_group(2, params_bind_group, &[params.offset]);
+                tracked_pass.draw(0..3, 0..1);
+            },
+        );
 
         Ok(())
     }
@@ -0,0 +1,148 @@
+use bevy::{
+    prelude::*,
+    render::{
+        camera::CameraProjection,
+        primitives::{Aabb, Frustum},
+        view::{RenderLayers, VisibleEntities},
+        Extract,
+    },
+    utils::HashSet,
+};
+
+use crate::{
+    outline::OutlineParams, transition::OutlineStyleBlend, CameraOutline, OutlineFilter,
+    OutlineProxy, OutlineProxyBounds, OutlineSettings, OutlineStyle, OutlineTransition,
+};
+
+/// Outlined entities culled from a camera's view, whose outline may still
+/// reach into it.
+#[derive(Clone, Debug, Default, Component)]
+pub(crate) struct OverscanVisibleEntities {
+    pub(crate) entities: Vec<Entity>,
+    // Width in pixels of the guard band on each side of the view. The render
+    // world sizes the outline textures from it, so they match the culling.
+    pub(crate) margin: u32,
+}
+
+type OverscanCameraQuery = (
+    &'static Camera,
+    &'static CameraOutline,
+    Option<&'static OutlineSettings>,
+    Option<&'static OutlineStyleBlend>,
+    Option<&'static OutlineTransition>,
+    &'static GlobalTransform,
+    &'static Projection,
+    &'static VisibleEntities,
+    Option<&'static RenderLayers>,
+    &'static mut OverscanVisibleEntities,
+);
+
+type OverscanMeshQuery = (
+    Entity,
+    &'static ComputedVisibility,
+    Option<&'static Aabb>,
+    Option<&'static OutlineProxy>,
+    &'static GlobalTransform,
+    Option<&'static RenderLayers>,
+);
+
+/// Finds the outlined entities within the guard band of each camera using
+/// overscan.
+pub(crate) fn expand_outline_visibility(
+    mut commands: Commands,
+    default_settings: Res<OutlineSettings>,
+    styles: Option<Res<Assets<OutlineStyle>>>,
+    proxy_bounds: Res<OutlineProxyBounds>,
+    new_cameras: Query<Entity, (With<CameraOutline>, Without<OverscanVisibleEntities>)>,
+    mut cameras: Query<OverscanCameraQuery>,
+    meshes: Query<OverscanMeshQuery, OutlineFilter>,
+) {
+    for entity in new_cameras.iter() {
+        commands
+            .entity(entity)
+            .insert(OverscanVisibleEntities::default());
+    }
+
+    for (
+        camera,
+        outline,
+        settings,
+        blend,
+        transition,
+        transform,
+        projection,
+        visible_entities,
+        view_layers,
+        mut overscan,
+    ) in cameras.iter_mut()
+    {
+        overscan.entities.clear();
+        overscan.margin = 0;
+
+        let settings = settings.unwrap_or(&default_settings);
+        if !camera.is_active || !outline.enabled || !settings.overscan {
+            continue;
+        }
+        let Some(styles) = &styles else {
+            continue;
+        };
+        let Some(style) = styles.get(&outline.style) else {
+            continue;
+        };
+        let Some(size) = camera.physical_viewport_size() else {
+            continue;
+        };
+
+        // Outlines reach as far as the current blend of their style.
+        let params = match (blend, transition) {
+            (Some(blend), Some(transition)) => blend.current(transition, styles),
+            _ => OutlineParams::from_style(style),
+        };
+        let margin = params.overscan();
+        if margin == 0 {
+            continue;
+        }
+        overscan.margin = margin;
+
+        // The projection is shrunk like that of the mask, so the frustum covers
+        // the guard band.
+        let size = size.as_vec2();
+        let scale = size / (size + 2.0 * margin as f32);
+        let view_proj = camera.projection_matrix() * transform.compute_matrix().inverse();
+        let frustum = Frustum::from_view_projection_custom_far(
+            &(Mat4::from_scale(scale.extend(1.0)) * view_proj),
+            &transform.translation(),
+            &transform.back(),
+            projection.far(),
+        );
+
+        let view_layers = view_layers.copied().unwrap_or_default();
+        let visible: HashSet<Entity> = visible_entities.entities.iter().copied().collect();
+        overscan.entities.extend(meshes.iter().filter_map(
+            |(entity, visibility, aabb, proxy, transform, layers)| {
+                // Hidden entities stay hidden, and visible ones are drawn anyway.
+                let culled = visibility.is_visible_in_hierarchy()
+                    && view_layers.intersects(&layers.copied().unwrap_or_default())
+                    && !visible.contains(&entity);
+                // Entities are drawn with their proxy, if they have one.
+                let aabb = match proxy {
+                    Some(proxy) => proxy_bounds.get(&proxy.0)?,
+                    None => *aabb?,
+                };
+                let model = transform.compute_matrix();
+                (culled && frustum.intersects_obb(&aabb, &model, true, false)).then_some(entity)
+            },
+        ));
+    }
+}
+
+pub(crate) fn extract_overscan_entities(
+    mut commands: Commands,
+    cameras: Extract<Query<(Entity, &OverscanVisibleEntities)>>,
+) {
+    for (entity, overscan) in cameras.iter() {
+        if overscan.margin > 0 {
+            commands.get_or_spawn(entity).insert(overscan.clone());
+        }
+    }
+}
@@ -0,0 +1,518 @@
+use bevy::{
+    input::InputSystem,
+    math::Ray,
+    prelude::*,
+    render::{
+        camera::{RenderTarget, Viewport},
+        primitives::Aabb,
+        view::RenderLayers,
+    },
+    utils::HashMap,
+    window::PrimaryWindow,
+};
+
+use crate::{CameraOutline, Outline, OutlineStyle};
+
+/// Plugin managing [`Outline`]s of entities under the cursor, or selected by
+/// clicking.
+///
+/// Entities are picked by casting a ray from each active camera with a
+/// [`CameraOutline`] against the `Aabb`s of visible meshes. Picking a mesh also
+/// picks its ancestors, so the components can be added to the root of a
+/// spawned scene alongside [`OutlinePropagation::Descendants`].
+///
+/// [`OutlinePropagation::Descendants`]: crate::OutlinePropagation::Descendants
+#[derive(Default)]
+pub struct OutlinePickingPlugin;
+
+/// Component for outlining an entity while the cursor is over it.
+///
+/// While hovering, the camera under the cursor switches to the given style,
+/// and back once the cursor leaves. The default handle keeps the camera's
+/// style. As styles are tied to the camera, other outlines drawn by the camera
+/// would also change style, so the style isn't switched while any entity is
+/// [`OutlineSelected`]. Hovered and selected outlines are then drawn together
+/// in the camera's style.
+#[derive(Clone, Debug, Default, PartialEq, Component, Reflect, FromReflect)]
+#[reflect(Component, Default)]
+pub struct OutlineOnHover(pub Handle<OutlineStyle>);
+
+/// Component for outlining an entity while it is [`OutlineSelected`].
+///
+/// The [`Outline`] of a pickable entity is only written when it is hovered,
+/// selected or deselected, so it can still be toggled by hand in between.
+///
+/// Clicking the entity selects it and deselects all others. Clicking with
+/// `Shift` held toggles its selection instead, and clicking empty space clears
+/// the selection.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Component, Reflect, FromReflect)]
+#[reflect(Component, Default)]
+pub struct OutlineOnSelect;
+
+/// Marker for selected entities with [`OutlineOnSelect`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Component, Reflect, FromReflect)]
+#[reflect(Component, Default)]
+pub struct OutlineSelected;
+
+/// The pickable entity under the cursor, if any.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Resource)]
+pub struct OutlineHover {
+    /// The camera whose viewport contains the cursor.
+    pub camera: Option<Entity>,
+    /// The nearest entity under the cursor with [`OutlineOnHover`] or
+    /// [`OutlineOnSelect`].
+    pub entity: Option<Entity>,
+}
+
+/// Style of a camera before it was switched by [`OutlineOnHover`].
+#[derive(Component)]
+struct HoverBaseStyle(Handle<OutlineStyle>);
+
+type PickableFilter = Or<(With<OutlineOnHover>, With<OutlineOnSelect>)>;
+type PickableOutlineQuery = (
+    Entity,
+    Option<&'static mut Outline>,
+    Option<&'static OutlineOnHover>,
+    Option<&'static OutlineSelected>,
+);
+type MeshQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Aabb,
+        &'static GlobalTransform,
+        &'static ComputedVisibility,
+        Option<&'static RenderLayers>,
+    ),
+    With<Handle<Mesh>>,
+>;
+
+impl OutlinePickingPlugin {
+    fn hover_system(
+        mut hover: ResMut<OutlineHover>,
+        primary_window: Query<Entity, With<PrimaryWindow>>,
+        windows: Query<&Window>,
+        cameras: Query<(
+            Entity,
+            &Camera,
+            &GlobalTransform,
+            &CameraOutline,
+            Option<&RenderLayers>,
+        )>,
+        meshes: MeshQuery,
+        parents: Query<&Parent>,
+        pickable: Query<(), PickableFilter>,
+    ) {
+        let primary_window = primary_window.get_single().ok();
+        let mut result = OutlineHover::default();
+
+        // Later cameras are drawn on top.
+        let mut cameras: Vec<_> = cameras
+            .iter()
+            .filter(|(_, camera, _, outline, _)| camera.is_active && outline.enabled)
+            .collect();
+        cameras.sort_by_key(|(_, camera, ..)| std::cmp::Reverse(camera.order));
+
+        for (entity, camera, camera_transform, _, camera_layers) in cameras {
+            let RenderTarget::Window(window_ref) = &camera.target else {
+                continue;
+            };
+            let Some(window) = window_ref
+                .normalize(primary_window)
+                .and_then(|window| windows.get(window.entity()).ok())
+            else {
+                continue;
+            };
+            let Some(position) = window
+                .cursor_position()
+                .and_then(|cursor| viewport_position(camera, cursor, window.scale_factor() as f32))
+            else {
+                continue;
+            };
+            let Some(ray) = camera.viewport_to_world(camera_transform, position) else {
+                continue;
+            };
+
+            let camera_layers = camera_layers.copied().unwrap_or_default();
+            let hit = meshes
+                .iter()
+                .filter(|(_, _, _, visibility, layers)| {
+                    visibility.is_visible()
+                        && camera_layers.intersects(&layers.copied().unwrap_or_default())
+                })
+                .filter_map(|(entity, aabb, transform, ..)| {
+                    ray_aabb_distance(ray, aabb, transform).map(|t| (entity, t))
+                })
+                .min_by(|(_, a), (_, b)| a.total_cmp(b));
+
+            result = OutlineHover {
+                camera: Some(entity),
+                entity: hit.and_then(|(entity, _)| {
+                    std::iter::once(entity)
+                        .chain(parents.iter_ancestors(entity))
+                        .find(|&entity| pickable.contains(entity))
+                }),
+            };
+            break;
+        }
+
+        if *hover != result {
+            *hover = result;
+        }
+    }
+
+    fn select_system(
+        mut commands: Commands,
+        hover: Res<OutlineHover>,
+        mouse: Option<Res<Input<MouseButton>>>,
+        keyboard: Option<Res<Input<KeyCode>>>,
+        selectable: Query<Option<&OutlineSelected>, With<OutlineOnSelect>>,
+        selected: Query<Entity, With<OutlineSelected>>,
+    ) {
+        if !mouse.is_some_and(|mouse| mouse.just_pressed(MouseButton::Left))
+            || hover.camera.is_none()
+        {
+            return;
+        }
+
+        let toggle = keyboard
+            .is_some_and(|keyboard| keyboard.any_pressed([KeyCode::LShift, KeyCode::RShift]));
+        let clicked = hover
+            .entity
+            .and_then(|entity| selectable.get(entity).ok().map(|s| (entity, s.is_some())));
+
+        if !toggle {
+            for entity in selected.iter() {
+                if clicked.map(|(clicked, _)| clicked) != Some(entity) {
+                    commands.entity(entity).remove::<OutlineSelected>();
+                }
+            }
+        }
+
+        match clicked {
+            Some((entity, true)) if toggle => {
+                commands.entity(entity).remove::<OutlineSelected>();
+            }
+            Some((entity, _)) => {
+                commands.entity(entity).insert(OutlineSelected);
+            }
+            None => {}
+        }
+    }
+
+    fn update_outlines_system(
+        mut commands: Commands,
+        hover: Res<OutlineHover>,
+        mut picked: Local<HashMap<Entity, bool>>,
+        mut outlines: Query<PickableOutlineQuery, PickableFilter>,
+    ) {
+        for (entity, outline, on_hover, selected) in outlines.iter_mut() {
+            let enabled =
+                (on_hover.is_some() && hover.entity == Some(entity)) || selected.is_some();
+            if picked.insert(entity, enabled) == Some(enabled) {
+                continue;
+            }
+
+            match outline {
+                Some(mut outline) => {
+                    outline.set_if_neq(Outline { enabled });
+                }
+                None => {
+                    commands.entity(entity).insert(Outline { enabled });
+                }
+            }
+        }
+
+        picked.retain(|entity, _| outlines.contains(*entity));
+    }
+
+    fn update_camera_styles_system(
+        mut commands: Commands,
+        hover: Res<OutlineHover>,
+        on_hover: Query<&OutlineOnHover>,
+        selected: Query<(), With<OutlineSelected>>,
+        mut cameras: Query<(Entity, &mut CameraOutline, Option<&HoverBaseStyle>)>,
+    ) {
+        let hover_style = hover
+            .entity
+            .and_then(|entity| on_hover.get(entity).ok())
+            .map(|on_hover| &on_hover.0)
+            .filter(|style| **style != Handle::default() && selected.is_empty());
+
+        for (entity, mut outline, base) in cameras.iter_mut() {
+            match hover_style.filter(|_| hover.camera == Some(entity)) {
+                Some(style) => {
+                    if base.is_none() {
+                        commands
+                            .entity(entity)
+                            .insert(HoverBaseStyle(outline.style.clone()));
+                    }
+                    if outline.style != *style {
+                        outline.style = style.clone();
+                    }
+                }
+                None => {
+                    if let Some(base) = base {
+                        outline.style = base.0.clone();
+                        commands.entity(entity).remove::<HoverBaseStyle>();
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Plugin for OutlinePickingPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<OutlineOnHover>()
+            .register_type::<OutlineOnSelect>()
+            .register_type::<OutlineSelected>()
+            .init_resource::<OutlineHover>()
+            .add_systems(
+                (
+                    Self::hover_system,
+                    Self::select_system,
+                    apply_system_buffers,
+                    Self::update_outlines_system,
+                    Self::update_camera_styles_system,
+                )
+                    .chain()
+                    .in_base_set(CoreSet::PreUpdate)
+                    .after(InputSystem),
+            );
+    }
+}
+
+// Converts a cursor position, with the origin at the bottom left of the window,
+// to a position in the camera's viewport.
+fn viewport_position(camera: &Camera, cursor: Vec2, scale_factor: f32) -> Option<Vec2> {
+    let target = camera.physical_target_size()?;
+    target_viewport_position(target, camera.viewport.as_ref(), cursor, scale_factor)
+}
+
+// Converts a cursor position in logical pixels to a position in `viewport`,
+// which covers all of a target of `target` physical pixels if `None`.
+fn target_viewport_position(
+    target: UVec2,
+    viewport: Option<&Viewport>,
+    cursor: Vec2,
+    scale_factor: f32,
+) -> Option<Vec2> {
+    let (min, size) = match viewport {
+        Some(viewport) => {
+            // Viewports are positioned from the top left.
+            let bottom = target
+                .y
+                .saturating_sub(viewport.physical_position.y + viewport.physical_size.y);
+            (
+                UVec2::new(viewport.physical_position.x, bottom),
+                viewport.physical_size,
+            )
+        }
+        None => (UVec2::ZERO, target),
+    };
+
+    let position = cursor - min.as_vec2() / scale_factor;
+    let size = size.as_vec2() / scale_factor;
+    (position.cmpge(Vec2::ZERO).all() && position.cmple(size).all()).then_some(position)
+}
+
+// Returns the distance along `ray` to the transformed `aabb`, if they
+// intersect.
+fn ray_aabb_distance(ray: Ray, aabb: &Aabb, transform: &GlobalTransform) -> Option<f32> {
+    let world_to_local = transform.compute_matrix().inverse();
+    // The direction isn't normalized, so distances stay in world units.
+    let origin = world_to_local.transform_point3(ray.origin) - Vec3::from(aabb.center);
+    let direction = world_to_local.transform_vector3(ray.direction);
+    let half_extents = Vec3::from(aabb.half_extents);
+
+    let inv_direction = direction.recip();
+    let t0 = (-half_extents - origin) * inv_direction;
+    let t1 = (half_extents - origin) * inv_direction;
+    let near = t0.min(t1).max_element();
+    let far = t0.max(t1).min_element();
+
+    (near <= far && far >= 0.0).then_some(near.max(0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{FRAC_PI_4, SQRT_2};
+
+    use bevy::asset::HandleId;
+
+    use super::*;
+
+    // Unit cube centered on the origin of its mesh.
+    fn cube() -> Aabb {
+        Aabb::from_min_max(Vec3::splat(-1.0), Vec3::splat(1.0))
+    }
+
+    // Ray cast towards -Z from `origin`.
+    fn ray_from(origin: Vec3) -> Ray {
+        Ray {
+            origin,
+            direction: Vec3::NEG_Z,
+        }
+    }
+
+    fn assert_near(actual: Option<f32>, expected: f32) {
+        let actual = actual.expect("expected a hit");
+        assert!((actual - expected).abs() < 1e-5, "{actual} != {expected}");
+    }
+
+    // The rays are parallel to the X and Y faces, so the slab test divides by
+    // zero for those axes.
+    #[test]
+    fn ray_hits_translated_aabb() {
+        let transform = GlobalTransform::from_translation(Vec3::new(0.0, 0.0, -5.0));
+        assert_near(
+            ray_aabb_distance(ray_from(Vec3::new(0.5, 0.5, 5.0)), &cube(), &transform),
+            9.0,
+        );
+        assert_eq!(
+            ray_aabb_distance(ray_from(Vec3::new(1.5, 0.0, 5.0)), &cube(), &transform),
+            None
+        );
+    }
+
+    #[test]
+    fn ray_hits_rotated_and_scaled_aabb() {
+        // Rotated about Y, the nearest edge of the cube sticks out to Z = √2.
+        let rotated = GlobalTransform::from_rotation(Quat::from_rotation_y(FRAC_PI_4));
+        assert_near(
+            ray_aabb_distance(ray_from(Vec3::new(0.0, 0.0, 10.0)), &cube(), &rotated),
+            10.0 - SQRT_2,
+        );
+        // Off center, the ray hits a slanted face, and misses past the edge
+        // at X = √2.
+        assert_near(
+            ray_aabb_distance(ray_from(Vec3::new(1.2, 0.0, 10.0)), &cube(), &rotated),
+            10.0 - (SQRT_2 - 1.2),
+        );
+        assert_eq!(
+            ray_aabb_distance(ray_from(Vec3::new(1.5, 0.0, 10.0)), &cube(), &rotated),
+            None
+        );
+
+        let scaled = GlobalTransform::from_scale(Vec3::splat(2.0));
+        assert_near(
+            ray_aabb_distance(ray_from(Vec3::new(1.5, 1.5, 10.0)), &cube(), &scaled),
+            8.0,
+        );
+        assert_eq!(
+            ray_aabb_distance(ray_from(Vec3::new(2.5, 0.0, 10.0)), &cube(), &scaled),
+            None
+        );
+    }
+
+    #[test]
+    fn ray_from_inside_hits_at_origin() {
+        let transform = GlobalTransform::IDENTITY;
+        assert_near(
+            ray_aabb_distance(ray_from(Vec3::ZERO), &cube(), &transform),
+            0.0,
+        );
+        // Boxes behind the ray are missed.
+        assert_eq!(
+            ray_aabb_distance(ray_from(Vec3::new(0.0, 0.0, -2.0)), &cube(), &transform),
+            None
+        );
+    }
+
+    #[test]
+    fn cursor_maps_into_offset_viewport() {
+        let target = UVec2::new(800, 600);
+        assert_eq!(
+            target_viewport_position(target, None, Vec2::new(10.0, 20.0), 1.0),
+            Some(Vec2::new(10.0, 20.0))
+        );
+
+        // With a scale factor of 2, the viewport's bottom left is at (50, 225)
+        // in logical pixels, and it is 100 by 50 logical pixels.
+        let viewport = Viewport {
+            physical_position: UVec2::new(100, 50),
+            physical_size: UVec2::new(200, 100),
+            ..default()
+        };
+        let position =
+            |x, y| target_viewport_position(target, Some(&viewport), Vec2::new(x, y), 2.0);
+        assert_eq!(position(60.0, 230.0), Some(Vec2::new(10.0, 5.0)));
+        assert_eq!(position(150.0, 275.0), Some(Vec2::new(100.0, 50.0)));
+        assert_eq!(position(40.0, 230.0), None);
+        assert_eq!(position(160.0, 230.0), None);
+        assert_eq!(position(60.0, 280.0), None);
+    }
+
+    fn picking_app() -> App {
+        let mut app = App::new();
+        app.init_resource::<OutlineHover>().add_systems(
+            (
+                OutlinePickingPlugin::update_outlines_system,
+                OutlinePickingPlugin::update_camera_styles_system,
+            )
+                .chain(),
+        );
+        app
+    }
+
+    fn enabled(app: &App, entity: Entity) -> bool {
+        app.world.get::<Outline>(entity).unwrap().enabled
+    }
+
+    #[test]
+    fn outlines_are_only_written_when_picking_changes() {
+        let mut app = picking_app();
+        let entity = app.world.spawn(OutlineOnSelect).id();
+        app.update();
+        assert!(!enabled(&app, entity));
+
+        // Toggled by hand, the outline is kept until the entity is picked.
+        app.world.get_mut::<Outline>(entity).unwrap().enabled = true;
+        app.update();
+        assert!(enabled(&app, entity));
+
+        app.world.entity_mut(entity).insert(OutlineSelected);
+        app.update();
+        app.world.entity_mut(entity).remove::<OutlineSelected>();
+        app.update();
+        assert!(!enabled(&app, entity));
+    }
+
+    #[test]
+    fn hover_style_is_kept_off_while_selecting() {
+        let style = || Handle::weak(HandleId::random::<OutlineStyle>());
+        let (base, hover_style) = (style(), style());
+
+        let mut app = picking_app();
+        let camera = app
+            .world
+            .spawn(CameraOutline {
+                style: base.clone(),
+                ..default()
+            })
+            .id();
+        let hovered = app.world.spawn(OutlineOnHover(hover_style.clone())).id();
+        let selected = app.world.spawn((OutlineOnSelect, OutlineSelected)).id();
+        *app.world.resource_mut::<OutlineHover>() = OutlineHover {
+            camera: Some(camera),
+            entity: Some(hovered),
+        };
+
+        let camera_style = |app: &App| {
+            app.world
+                .get::<CameraOutline>(camera)
+                .unwrap()
+                .style
+                .clone()
+        };
+        app.update();
+        assert_eq!(camera_style(&app), base);
+        assert!(enabled(&app, hovered) && enabled(&app, selected));
+
+        app.world.entity_mut(selected).remove::<OutlineSelected>();
+        app.update();
+        assert_eq!(camera_style(&app), hover_style);
+    }
+}
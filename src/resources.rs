@@ -7,15 +7,19 @@ use bevy::{
             BufferBindingType, DynamicUniformBuffer, Extent3d, FilterMode, Sampler,
             SamplerBindingType, SamplerDescriptor, ShaderStages, ShaderType, TextureDescriptor,
             TextureDimension, TextureFormat, TextureSampleType, TextureUsages, TextureView,
-            TextureViewDimension, UniformBuffer,
+            TextureViewDescriptor, TextureViewDimension, UniformBuffer,
         },
         renderer::{RenderDevice, RenderQueue},
         texture::{CachedTexture, TextureCache},
-        view::ExtractedWindows,
+        view::ExtractedView,
     },
+    utils::HashMap,
 };
 
-use crate::{jfa, outline, OutlineSettings, JFA_TEXTURE_FORMAT};
+use crate::{
+    jfa, outline, overscan::OverscanVisibleEntities, OutlineMethod, OutlineSettings,
+    MASK_TEXTURE_FORMAT, NORMAL_DEPTH_TEXTURE_FORMAT, OBJECT_ID_TEXTURE_FORMAT,
+};
 
 const JFA_FROM_PRIMARY: &str = "jfa_from_primary_output_bind_group";
 const JFA_FROM_SECONDARY: &str = "jfa_from_secondary_output_bind_group";
@@ -23,29 +27,63 @@ const JFA_OUTLINE_SRC: &str = "jfa_outline_src_bind_group";
 
 #[derive(Resource)]
 pub struct OutlineResources {
-    // Multisample target for initial mask pass.
-    pub mask_multisample: CachedTexture,
-    // Resolve target for the above.
-    pub mask_output: CachedTexture,
+    // 1x1 texture bound in place of object IDs when not separating objects,
+    // or of normals and depths without creases, and output by passes skipping
+    // a view.
+    pub placeholder: CachedTexture,
 
     pub dimensions_bind_group_layout: BindGroupLayout,
-    pub dimensions_buffer: UniformBuffer<jfa::Dimensions>,
-    pub dimensions_bind_group: BindGroup,
 
     // Non-filtering sampler for all sampling operations.
     pub sampler: Sampler,
 
-    // Bind group and layout for JFA init pass.
+    // Bind group layout for JFA init pass.
     pub jfa_init_bind_group_layout: BindGroupLayout,
-    pub jfa_init_bind_group: BindGroup,
+    // The above, reading multisampled object IDs.
+    pub jfa_init_multisampled_ids_bind_group_layout: BindGroupLayout,
 
     // Bind group layout for JFA iteration passes.
     pub jfa_bind_group_layout: BindGroupLayout,
+    // Bind group layout for merging the previous frame's flood into the seeds.
+    pub jfa_temporal_bind_group_layout: BindGroupLayout,
     // Dynamic uniform buffer containing power-of-two JFA distances from 1 to 32768.
     // TODO: use instance ID instead?
     pub jfa_distance_buffer: DynamicUniformBuffer<jfa::JumpDist>,
     pub jfa_distance_offsets: Vec<u32>,
 
+    // Bind group layout for sampling JFA results in the outline shader.
+    pub outline_src_bind_group_layout: BindGroupLayout,
+    // The above, reading multisampled object IDs.
+    pub outline_src_multisampled_ids_bind_group_layout: BindGroupLayout,
+    // Bind group layout for outline style parameters.
+    pub outline_params_bind_group_layout: BindGroupLayout,
+}
+
+/// Textures and bind groups of the outline passes for a view, sized and
+/// formatted by the view's [`OutlineSettings`].
+#[derive(Clone, Component)]
+pub struct ViewOutlineTextures {
+    pub dimensions: jfa::Dimensions,
+    pub dimensions_bind_group: BindGroup,
+
+    // Multisample target for initial mask pass, if the mask is multisampled.
+    pub mask_multisample: Option<CachedTexture>,
+    // Resolve target for the above, or the target itself otherwise.
+    pub mask_output: CachedTexture,
+
+    // Target for per-object IDs, written alongside the mask when separating
+    // objects. It has as many samples as the mask, and isn't resolved, since
+    // averaging would mix the IDs of adjacent objects.
+    pub object_id_output: Option<CachedTexture>,
+
+    // Targets for normals and depths, and the depth buffer keeping those of
+    // the nearest surface, when drawing creases.
+    pub normal_depth_multisample: Option<CachedTexture>,
+    pub normal_depth_output: Option<CachedTexture>,
+    pub mask_depth: Option<CachedTexture>,
+
+    pub jfa_init_bind_group: BindGroup,
+
     // Bind group for jump flood passes targeting the primary output.
     pub jfa_from_secondary_bind_group: BindGroup,
     // Primary jump flood output.
@@ -56,16 +94,41 @@ pub struct OutlineResources {
     // Secondary jump flood output.
     pub jfa_secondary_output: CachedTexture,
 
-    // Bind groups for the final jump flood pass.
+    // Output of the final jump flood pass, at the full size of the view.
     pub jfa_final_output: CachedTexture,
 
-    // Bind group layout for sampling JFA results in the outline shader.
-    pub outline_src_bind_group_layout: BindGroupLayout,
-    // Bind group layout for outline style parameters.
-    pub outline_params_bind_group_layout: BindGroupLayout,
+    // Copy of the previous frame's final output, and the bind group for
+    // merging it into the seeds, when reusing the previous flood.
+    pub jfa_history: Option<CachedTexture>,
+    pub jfa_temporal_bind_group: Option<BindGroup>,
+
     pub outline_src_bind_group: BindGroup,
 }
 
+impl ViewOutlineTextures {
+    /// Returns all textures of the view.
+    pub(crate) fn textures(&self) -> impl Iterator<Item = &CachedTexture> {
+        self.cached_textures().chain(self.jfa_history.as_ref())
+    }
+
+    // Textures taken from the `TextureCache`, which may change between frames.
+    fn cached_textures(&self) -> impl Iterator<Item = &CachedTexture> {
+        [
+            self.mask_multisample.as_ref(),
+            Some(&self.mask_output),
+            self.object_id_output.as_ref(),
+            self.normal_depth_multisample.as_ref(),
+            self.normal_depth_output.as_ref(),
+            self.mask_depth.as_ref(),
+            Some(&self.jfa_primary_output),
+            Some(&self.jfa_secondary_output),
+            Some(&self.jfa_final_output),
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
 impl OutlineResources {
     fn create_jfa_bind_group(
         &self,
@@ -84,7 +147,7 @@ impl OutlineResources {
     }
 }
 
-fn create_jfa_bind_group(
+pub(crate) fn create_jfa_bind_group(
     device: &RenderDevice,
     layout: &BindGroupLayout,
     label: &str,
@@ -112,12 +175,42 @@ fn create_jfa_bind_group(
     })
 }
 
+pub(crate) fn create_jfa_init_bind_group(
+    device: &RenderDevice,
+    layout: &BindGroupLayout,
+    mask: &TextureView,
+    object_id: &TextureView,
+    sampler: &Sampler,
+) -> BindGroup {
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("outline_jfa_init_bind_group"),
+        layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(mask),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: BindingResource::Sampler(sampler),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: BindingResource::TextureView(object_id),
+            },
+        ],
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
 fn create_outline_src_bind_group(
     device: &RenderDevice,
     layout: &BindGroupLayout,
     label: &str,
     src: &TextureView,
     mask: &TextureView,
+    object_id: &TextureView,
+    normal_depth: &TextureView,
     sampler: &Sampler,
 ) -> BindGroup {
     device.create_bind_group(&BindGroupDescriptor {
@@ -136,6 +229,14 @@ fn create_outline_src_bind_group(
                 binding: 2,
                 resource: BindingResource::Sampler(sampler),
             },
+            BindGroupEntry {
+                binding: 3,
+                resource: BindingResource::TextureView(object_id),
+            },
+            BindGroupEntry {
+                binding: 4,
+                resource: BindingResource::TextureView(normal_depth),
+            },
         ],
     })
 }
@@ -152,25 +253,18 @@ impl FromWorld for OutlineResources {
         let queue = world.get_resource::<RenderQueue>().unwrap().clone();
         let mut textures = world.get_resource_mut::<TextureCache>().unwrap();
 
-        let mask_output_desc = tex_desc("outline_mask_output", size, TextureFormat::R8Unorm);
-        let mask_multisample_desc = TextureDescriptor {
-            label: Some("outline_mask_multisample"),
-            sample_count: 4,
-            ..mask_output_desc.clone()
-        };
-        let mask_multisample = textures.get(&device, mask_multisample_desc);
-        let mask_output = textures.get(&device, mask_output_desc);
-
-        let dims = jfa::Dimensions::new(size.width, size.height);
-        let mut dimensions_buffer = UniformBuffer::from(dims);
-        dimensions_buffer.write_buffer(&device, &queue);
+        let placeholder = textures.get(
+            &device,
+            tex_desc("outline_placeholder", size, OBJECT_ID_TEXTURE_FORMAT),
+        );
 
         let dimensions_bind_group_layout =
             device.create_bind_group_layout(&BindGroupLayoutDescriptor {
                 label: Some("jfa_dimensions_bind_group_layout"),
                 entries: &[BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: ShaderStages::FRAGMENT,
+                    // Also read when drawing the mask, for the overscan.
+                    visibility: ShaderStages::VERTEX_FRAGMENT,
                     ty: BindingType::Buffer {
                         ty: BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -180,15 +274,6 @@ impl FromWorld for OutlineResources {
                 }],
             });
 
-        let dimensions_bind_group = device.create_bind_group(&BindGroupDescriptor {
-            label: Some("jfa_dimensions_bind_group"),
-            layout: &dimensions_bind_group_layout,
-            entries: &[BindGroupEntry {
-                binding: 0,
-                resource: dimensions_buffer.binding().unwrap(),
-            }],
-        });
-
         let sampler = device.create_sampler(&SamplerDescriptor {
             label: Some("outline_jfa_sampler"),
             address_mode_u: AddressMode::ClampToEdge,
@@ -201,7 +286,7 @@ impl FromWorld for OutlineResources {
             ..Default::default()
         });
 
-        let jfa_init_bind_group_layout =
+        let create_jfa_init_layout = |multisampled_ids: bool| {
             device.create_bind_group_layout(&BindGroupLayoutDescriptor {
                 label: Some("outline_jfa_init_bind_group_layout"),
                 entries: &[
@@ -221,23 +306,22 @@ impl FromWorld for OutlineResources {
                         ty: BindingType::Sampler(SamplerBindingType::NonFiltering),
                         count: None,
                     },
+                    // Object IDs
+                    BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Texture {
+                            sample_type: TextureSampleType::Float { filterable: false },
+                            view_dimension: TextureViewDimension::D2,
+                            multisampled: multisampled_ids,
+                        },
+                        count: None,
+                    },
                 ],
-            });
-        let jfa_init_bind_group = device.create_bind_group(&BindGroupDescriptor {
-            label: Some("outline_jfa_init_bind_group"),
-            layout: &jfa_init_bind_group_layout,
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: BindingResource::TextureView(&mask_output.default_view),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: BindingResource::Sampler(&sampler),
-                },
-            ],
-        });
-
+            })
+        };
+        let jfa_init_bind_group_layout = create_jfa_init_layout(false);
+        let jfa_init_multisampled_ids_bind_group_layout = create_jfa_init_layout(true);
         let jfa_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
             label: Some("outline_jfa_bind_group_layout"),
             entries: &[
@@ -269,6 +353,41 @@ impl FromWorld for OutlineResources {
                 },
             ],
         });
+        let jfa_temporal_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("outline_jfa_temporal_bind_group_layout"),
+                entries: &[
+                    // Seeds
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Texture {
+                            sample_type: TextureSampleType::Float { filterable: false },
+                            view_dimension: TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    // History
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Texture {
+                            sample_type: TextureSampleType::Float { filterable: false },
+                            view_dimension: TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Sampler(SamplerBindingType::NonFiltering),
+                        count: None,
+                    },
+                ],
+            });
+
         let mut jfa_distance_buffer = DynamicUniformBuffer::default();
         let mut jfa_distance_offsets = Vec::new();
         for exp in 0_u32..16 {
@@ -281,39 +400,7 @@ impl FromWorld for OutlineResources {
         }
         jfa_distance_buffer.write_buffer(&device, &queue);
 
-        let jfa_primary_output_desc =
-            tex_desc("outline_jfa_primary_output", size, JFA_TEXTURE_FORMAT);
-        let jfa_primary_output = textures.get(&device, jfa_primary_output_desc);
-        let jfa_secondary_output_desc =
-            tex_desc("outline_jfa_secondary_output", size, JFA_TEXTURE_FORMAT);
-        let jfa_secondary_output = textures.get(&device, jfa_secondary_output_desc);
-        let jfa_final_output_desc = tex_desc("outline_jfa_final_output", size, JFA_TEXTURE_FORMAT);
-        let jfa_final_output = textures.get(&device, jfa_final_output_desc);
-
-        let jfa_from_secondary_bind_group = create_jfa_bind_group(
-            &device,
-            &jfa_bind_group_layout,
-            "outline_jfa_primary_bind_group",
-            jfa_distance_buffer.binding().unwrap(),
-            &jfa_secondary_output.default_view,
-            &sampler,
-        );
-        let jfa_from_primary_bind_group = create_jfa_bind_group(
-            &device,
-            &jfa_bind_group_layout,
-            "outline_jfa_secondary_bind_group",
-            jfa_distance_buffer.binding().unwrap(),
-            &jfa_primary_output.default_view,
-            &sampler,
-        );
-
-        let mut outline_params_buffer = UniformBuffer::from(outline::OutlineParams::new(
-            Color::hex("b4a2c8").unwrap(),
-            32.0,
-        ));
-        outline_params_buffer.write_buffer(&device, &queue);
-
-        let outline_src_bind_group_layout =
+        let create_outline_src_layout = |multisampled_ids: bool| {
             device.create_bind_group_layout(&BindGroupLayoutDescriptor {
                 label: Some("jfa_outline_bind_group_layout"),
                 entries: &[
@@ -346,20 +433,45 @@ impl FromWorld for OutlineResources {
                         ty: BindingType::Sampler(SamplerBindingType::NonFiltering),
                         count: None,
                     },
+                    // Object IDs
+                    BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Texture {
+                            sample_type: TextureSampleType::Float { filterable: false },
+                            view_dimension: TextureViewDimension::D2,
+                            multisampled: multisampled_ids,
+                        },
+                        count: None,
+                    },
+                    // Normals and depths
+                    BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Texture {
+                            sample_type: TextureSampleType::Float { filterable: false },
+                            view_dimension: TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
                 ],
-            });
+            })
+        };
+        let outline_src_bind_group_layout = create_outline_src_layout(false);
+        let outline_src_multisampled_ids_bind_group_layout = create_outline_src_layout(true);
 
         let outline_params_bind_group_layout =
             device.create_bind_group_layout(&BindGroupLayoutDescriptor {
                 label: Some("jfa_outline_params_bind_group_layout"),
                 entries: &[
-                    // OutlineParams
+                    // OutlineParams, also read when extruding hulls
                     BindGroupLayoutEntry {
                         binding: 0,
-                        visibility: ShaderStages::FRAGMENT,
+                        visibility: ShaderStages::VERTEX_FRAGMENT,
                         ty: BindingType::Buffer {
                             ty: BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
+                            has_dynamic_offset: true,
                             min_binding_size: Some(outline::OutlineParams::min_size()),
                         },
                         count: None,
@@ -367,148 +479,284 @@ impl FromWorld for OutlineResources {
                 ],
             });
 
-        let outline_src_bind_group = create_outline_src_bind_group(
-            &device,
-            &outline_src_bind_group_layout,
-            "jfa_outline_src_bind_group",
-            &jfa_final_output.default_view,
-            &mask_output.default_view,
-            &sampler,
-        );
-
         OutlineResources {
-            mask_multisample,
-            mask_output,
+            placeholder,
             dimensions_bind_group_layout,
-            dimensions_buffer,
-            dimensions_bind_group,
+            sampler,
             jfa_init_bind_group_layout,
-            jfa_init_bind_group,
+            jfa_init_multisampled_ids_bind_group_layout,
             jfa_bind_group_layout,
-            sampler,
+            jfa_temporal_bind_group_layout,
             jfa_distance_buffer,
             jfa_distance_offsets,
-            jfa_primary_output,
-            jfa_secondary_output,
-            jfa_final_output,
-            jfa_from_secondary_bind_group,
-            jfa_from_primary_bind_group,
             outline_src_bind_group_layout,
+            outline_src_multisampled_ids_bind_group_layout,
             outline_params_bind_group_layout,
-            outline_src_bind_group,
         }
     }
 }
 
-pub fn recreate_outline_resources(
-    settings: Res<OutlineSettings>,
-    mut outline: ResMut<OutlineResources>,
+type OutlineTexturesViewQuery = (
+    Entity,
+    &'static ExtractedView,
+    &'static OutlineSettings,
+    Option<&'static OverscanVisibleEntities>,
+);
+
+#[allow(clippy::too_many_arguments)]
+pub fn prepare_view_outline_textures(
+    mut commands: Commands,
+    // Textures of each view in the previous frame.
+    mut cache: Local<HashMap<Entity, ViewOutlineTextures>>,
+    res: Res<OutlineResources>,
     device: Res<RenderDevice>,
     queue: Res<RenderQueue>,
     mut textures: ResMut<TextureCache>,
-    windows: Res<ExtractedWindows>,
+    views: Query<OutlineTexturesViewQuery>,
 ) {
-    let Some(primary_entity) = windows.primary else {
-        return
-    };
-
-    let Some(primary) = windows.get(&primary_entity) else {
-        return;
-    };
-
-    let half_size = Extent3d {
-        width: primary.physical_width / 2,
-        height: primary.physical_height / 2,
-        depth_or_array_layers: 1,
-    };
-
-    let size = Extent3d {
-        width: primary.physical_width,
-        height: primary.physical_height,
-        depth_or_array_layers: 1,
-    };
-
-    let half_resolution = settings.half_resolution;
-    let jfa_size = if half_resolution { half_size } else { size };
-
-    let new_dims = jfa::Dimensions::new(size.width, size.height);
-    let dims = outline.dimensions_buffer.get_mut();
-    if *dims != new_dims {
-        *dims = new_dims;
-        outline.dimensions_buffer.write_buffer(&device, &queue);
-    }
+    cache.retain(|entity, _| views.contains(*entity));
 
-    let old_mask = outline.mask_multisample.texture.id();
-    let mask_output_desc = tex_desc("outline_mask_output", size, TextureFormat::R8Unorm);
-    let mask_multisample_desc = TextureDescriptor {
-        label: Some("outline_mask_multisample"),
-        sample_count: 4,
-        ..mask_output_desc.clone()
-    };
-
-    // Recreate mask output targets.
-    outline.mask_output = textures.get(&device, mask_output_desc);
-    outline.mask_multisample = textures.get(&device, mask_multisample_desc);
-
-    if outline.mask_output.texture.id() != old_mask {
-        // Recreate JFA init pass bind group
-        outline.jfa_init_bind_group = device.create_bind_group(&BindGroupDescriptor {
-            label: Some("outline_jfa_init_bind_group"),
-            layout: &outline.jfa_init_bind_group_layout,
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: BindingResource::TextureView(&outline.mask_output.default_view),
+    for (entity, view, settings, overscan) in views.iter() {
+        // Hull outlines don't use the mask or jump flood.
+        if settings.method != OutlineMethod::JumpFlood {
+            continue;
+        }
+
+        // The textures extend past the view on each side by the guard band
+        // culling was widened by.
+        let overscan = overscan.map_or(0, |overscan| overscan.margin);
+        let size = Extent3d {
+            width: view.viewport.z.max(1) + 2 * overscan,
+            height: view.viewport.w.max(1) + 2 * overscan,
+            depth_or_array_layers: 1,
+        };
+        let scale =
+            |length: u32| ((length as f32 * settings.resolution_scale).ceil() as u32).max(1);
+        let jfa_size = Extent3d {
+            width: scale(size.width),
+            height: scale(size.height),
+            depth_or_array_layers: 1,
+        };
+        let samples = settings.mask_samples();
+        let jfa_format = settings.precision.jfa_texture_format();
+
+        let mut get_targets = |labels: [&'static str; 2], format: TextureFormat| {
+            let output_desc = tex_desc(labels[0], size, format);
+            // The multisample target is resolved to the output.
+            let multisample = (samples > 1).then(|| {
+                textures.get(
+                    &device,
+                    TextureDescriptor {
+                        label: Some(labels[1]),
+                        sample_count: samples,
+                        ..output_desc.clone()
+                    },
+                )
+            });
+            (multisample, textures.get(&device, output_desc))
+        };
+        let (mask_multisample, mask_output) = get_targets(
+            ["outline_mask_output", "outline_mask_multisample"],
+            MASK_TEXTURE_FORMAT,
+        );
+        // Normals and depths are only written when drawing creases.
+        let (normal_depth_multisample, normal_depth_output) = if settings.creases {
+            let (multisample, output) = get_targets(
+                [
+                    "outline_normal_depth_output",
+                    "outline_normal_depth_multisample",
+                ],
+                NORMAL_DEPTH_TEXTURE_FORMAT,
+            );
+            (multisample, Some(output))
+        } else {
+            (None, None)
+        };
+        // Object IDs are only written when separating objects.
+        let object_id_output = settings.separate_objects.then(|| {
+            textures.get(
+                &device,
+                TextureDescriptor {
+                    sample_count: samples,
+                    ..tex_desc("outline_object_id_output", size, OBJECT_ID_TEXTURE_FORMAT)
                 },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: BindingResource::Sampler(&outline.sampler),
+            )
+        });
+        let mask_depth = settings.creases.then(|| {
+            textures.get(
+                &device,
+                TextureDescriptor {
+                    sample_count: samples,
+                    usage: TextureUsages::RENDER_ATTACHMENT,
+                    ..tex_desc("outline_mask_depth", size, TextureFormat::Depth32Float)
                 },
-            ],
+            )
         });
-    }
 
-    let old_jfa_primary = outline.jfa_primary_output.texture.id();
-    let jfa_primary_desc = tex_desc("outline_jfa_primary_output", jfa_size, JFA_TEXTURE_FORMAT);
-    let jfa_primary_output = textures.get(&device, jfa_primary_desc);
-    if jfa_primary_output.texture.id() != old_jfa_primary {
-        outline.jfa_primary_output = jfa_primary_output;
-        outline.jfa_from_primary_bind_group = outline.create_jfa_bind_group(
+        let jfa_primary_output = textures.get(
             &device,
-            JFA_FROM_PRIMARY,
-            &outline.jfa_primary_output.default_view,
+            tex_desc("outline_jfa_primary_output", jfa_size, jfa_format),
         );
-    }
+        let jfa_secondary_output = textures.get(
+            &device,
+            tex_desc("outline_jfa_secondary_output", jfa_size, jfa_format),
+        );
+        let mut jfa_final_desc = tex_desc("outline_jfa_final_output", size, jfa_format);
+        if settings.temporal {
+            // Copied to the history for the next frame.
+            jfa_final_desc.usage |= TextureUsages::COPY_SRC;
+        }
+        let jfa_final_output = textures.get(&device, jfa_final_desc);
+
+        let dimensions = jfa::Dimensions::new(size.width, size.height).with_overscan(overscan);
+        let texture_ids: Vec<_> = [
+            mask_multisample.as_ref(),
+            Some(&mask_output),
+            object_id_output.as_ref(),
+            normal_depth_multisample.as_ref(),
+            normal_depth_output.as_ref(),
+            mask_depth.as_ref(),
+            Some(&jfa_primary_output),
+            Some(&jfa_secondary_output),
+            Some(&jfa_final_output),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|texture| texture.texture.id())
+        .collect();
+
+        // Bind groups are only recreated when the textures change.
+        if let Some(cached) = cache.get(&entity) {
+            let cached_ids = cached.cached_textures().map(|texture| texture.texture.id());
+            if cached_ids.eq(texture_ids.iter().copied())
+                && cached.dimensions == dimensions
+                && cached.jfa_history.is_some() == settings.temporal
+            {
+                commands.entity(entity).insert(cached.clone());
+                continue;
+            }
+        }
 
-    let old_jfa_secondary = outline.jfa_secondary_output.texture.id();
-    let jfa_secondary_desc = tex_desc("outline_jfa_secondary_output", jfa_size, JFA_TEXTURE_FORMAT);
-    let jfa_secondary_output = textures.get(&device, jfa_secondary_desc);
-    if jfa_secondary_output.texture.id() != old_jfa_secondary {
-        outline.jfa_secondary_output = jfa_secondary_output;
-        outline.jfa_from_secondary_bind_group = outline.create_jfa_bind_group(
+        // The dimensions only change with the textures.
+        let mut dimensions_buffer = UniformBuffer::from(dimensions);
+        dimensions_buffer.write_buffer(&device, &queue);
+        let dimensions_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("jfa_dimensions_bind_group"),
+            layout: &res.dimensions_bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: dimensions_buffer.binding().unwrap(),
+            }],
+        });
+
+        let object_id_view = object_id_output
+            .as_ref()
+            .unwrap_or(&res.placeholder)
+            .default_view
+            .clone();
+        let normal_depth_view = normal_depth_output
+            .as_ref()
+            .unwrap_or(&res.placeholder)
+            .default_view
+            .clone();
+        let (jfa_init_layout, outline_src_layout) = if settings.multisampled_ids() {
+            (
+                &res.jfa_init_multisampled_ids_bind_group_layout,
+                &res.outline_src_multisampled_ids_bind_group_layout,
+            )
+        } else {
+            (
+                &res.jfa_init_bind_group_layout,
+                &res.outline_src_bind_group_layout,
+            )
+        };
+        let jfa_init_bind_group = create_jfa_init_bind_group(
+            &device,
+            jfa_init_layout,
+            &mask_output.default_view,
+            &object_id_view,
+            &res.sampler,
+        );
+        let jfa_from_primary_bind_group =
+            res.create_jfa_bind_group(&device, JFA_FROM_PRIMARY, &jfa_primary_output.default_view);
+        let jfa_from_secondary_bind_group = res.create_jfa_bind_group(
             &device,
             JFA_FROM_SECONDARY,
-            &outline.jfa_secondary_output.default_view,
+            &jfa_secondary_output.default_view,
         );
-    }
-
-    let old_jfa_final = outline.jfa_final_output.texture.id();
-    let jfa_final_desc = tex_desc("outline_jfa_final_output", size, JFA_TEXTURE_FORMAT);
-    let jfa_final_output = textures.get(&device, jfa_final_desc);
-    if jfa_final_output.texture.id() != old_jfa_final {
-        outline.jfa_final_output = jfa_final_output;
-        outline.outline_src_bind_group = create_outline_src_bind_group(
+        let outline_src_bind_group = create_outline_src_bind_group(
             &device,
-            &outline.outline_src_bind_group_layout,
+            outline_src_layout,
             JFA_OUTLINE_SRC,
-            &outline.jfa_final_output.default_view,
-            &outline.mask_output.default_view,
-            &outline.sampler,
+            &jfa_final_output.default_view,
+            &mask_output.default_view,
+            &object_id_view,
+            &normal_depth_view,
+            &res.sampler,
         );
+
+        // The history outlives the frame, so isn't shared through the cache.
+        let jfa_history = settings.temporal.then(|| {
+            let texture = device.create_texture(&TextureDescriptor {
+                usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+                ..tex_desc("outline_jfa_history", size, jfa_format)
+            });
+            let default_view = texture.create_view(&TextureViewDescriptor::default());
+            CachedTexture {
+                texture,
+                default_view,
+            }
+        });
+        let jfa_temporal_bind_group = jfa_history.as_ref().map(|history| {
+            device.create_bind_group(&BindGroupDescriptor {
+                label: Some("outline_jfa_temporal_bind_group"),
+                layout: &res.jfa_temporal_bind_group_layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::TextureView(&jfa_primary_output.default_view),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::TextureView(&history.default_view),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: BindingResource::Sampler(&res.sampler),
+                    },
+                ],
+            })
+        });
+
+        let view_textures = ViewOutlineTextures {
+            dimensions,
+            dimensions_bind_group,
+            mask_multisample,
+            mask_output,
+            object_id_output,
+            normal_depth_multisample,
+            normal_depth_output,
+            mask_depth,
+            jfa_init_bind_group,
+            jfa_from_secondary_bind_group,
+            jfa_primary_output,
+            jfa_from_primary_bind_group,
+            jfa_secondary_output,
+            jfa_final_output,
+            jfa_history,
+            jfa_temporal_bind_group,
+            outline_src_bind_group,
+        };
+        commands.entity(entity).insert(view_textures.clone());
+        cache.insert(entity, view_textures);
     }
 }
 
-fn tex_desc(label: &'static str, size: Extent3d, format: TextureFormat) -> TextureDescriptor {
+pub(crate) fn tex_desc(
+    label: &'static str,
+    size: Extent3d,
+    format: TextureFormat,
+) -> TextureDescriptor {
     TextureDescriptor {
         label: Some(label),
         size,
@@ -7,6 +7,8 @@ struct Dimensions {
     inv_width: f32,
     // Reciprocal of height.
     inv_height: f32,
+    // Width in pixels of the guard band on each side of the view.
+    overscan: f32,
 };
 
 @group(0) @binding(0)
@@ -0,0 +1,63 @@
+// Hull outline shader.
+//
+// Meshes are first drawn without HULL, only writing their depth. With HULL,
+// their back faces are drawn extruded along the normals, showing only around
+// the meshes.
+
+#import bevy_render::view
+#import bevy_pbr::mesh_types
+
+@group(0) @binding(0)
+var<uniform> view: View;
+
+@group(1) @binding(0)
+var<uniform> mesh: Mesh;
+
+struct OutlineMesh {
+    // Nonzero object ID, unused by hulls.
+    id: u32,
+    // Opacity of the object's outline.
+    alpha: f32,
+};
+
+@group(2) @binding(0)
+var<uniform> outline_mesh: OutlineMesh;
+
+// The leading fields of the outline parameters.
+struct Params {
+    color: vec4<f32>,
+    // Outline weight in pixels.
+    weight: f32,
+};
+
+@group(3) @binding(0)
+var<uniform> params: Params;
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> @builtin(position) vec4<f32> {
+    var clip_position = view.view_proj * mesh.model * vec4<f32>(vertex.position, 1.0);
+#ifdef HULL
+    // The normal is projected to find the direction of the extrusion in
+    // pixels, which is then scaled by w to keep the width constant on screen.
+    let world_normal = (mesh.inverse_transpose_model * vec4<f32>(vertex.normal, 0.0)).xyz;
+    let pix_normal = (view.view_proj * vec4<f32>(world_normal, 0.0)).xy * view.viewport.zw;
+    if dot(pix_normal, pix_normal) > 0.0 {
+        let offset = normalize(pix_normal) * params.weight * 2.0 / view.viewport.zw;
+        clip_position = vec4<f32>(
+            clip_position.xy + offset * clip_position.w,
+            clip_position.zw,
+        );
+    }
+#endif
+    return clip_position;
+}
+
+@fragment
+fn fragment() -> @location(0) vec4<f32> {
+    return vec4<f32>(params.color.rgb, params.color.a * outline_mesh.alpha);
+}
@@ -0,0 +1,37 @@
+#import outline::fullscreen
+#import outline::dimensions
+
+// Converts a jump flood result into distances for image requests.
+struct DistanceParams {
+    // Distance in pixels mapped to the full output range.
+    spread: f32,
+};
+
+@group(1) @binding(0)
+var jfa_buffer: texture_2d<f32>;
+@group(1) @binding(1)
+var nearest_sampler: sampler;
+@group(1) @binding(2)
+var<uniform> params: DistanceParams;
+
+struct FragmentIn {
+    @location(0) texcoord: vec2<f32>,
+};
+
+@fragment
+fn fragment(in: FragmentIn) -> @location(0) vec4<f32> {
+    let fb_jfa_pos = textureSample(jfa_buffer, nearest_sampler, in.texcoord).xy;
+
+    // No seed within reach of the jump flood.
+    if fb_jfa_pos.x == -1.0 {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+
+    let fb_to_pix = vec2<f32>(dims.width, dims.height);
+    let delta = in.texcoord * fb_to_pix - fb_jfa_pos * fb_to_pix;
+    let mag = sqrt(dot(delta, delta));
+
+    // 1 at the edge of the source coverage, 0 at the spread.
+    let value = 1.0 - clamp(mag / params.spread, 0.0, 1.0);
+    return vec4<f32>(value, value, value, 1.0);
+}
@@ -6,6 +6,22 @@
 var mask_buffer: texture_2d<f32>;
 @group(1) @binding(1)
 var mask_sampler: sampler;
+@group(1) @binding(2)
+#ifdef MULTISAMPLED_IDS
+var object_id_buffer: texture_multisampled_2d<f32>;
+#else
+var object_id_buffer: texture_2d<f32>;
+#endif
+
+// Returns the ID of the object covering `coord`.
+//
+// IDs aren't resolved from their samples, since averaging would mix the IDs
+// of adjacent objects, so the first sample is read instead.
+fn object_id(coord: vec2<f32>) -> f32 {
+    let size = vec2<i32>(i32(dims.width), i32(dims.height));
+    let texel = clamp(vec2<i32>(coord * vec2<f32>(size)), vec2<i32>(0), size - 1);
+    return textureLoad(object_id_buffer, texel, 0).x;
+}
 
 struct FragmentIn {
     @location(0) texcoord: vec2<f32>,
@@ -37,7 +53,43 @@ fn fragment(in: FragmentIn) -> @location(0) vec4<f32> {
     samples[2][2] = textureSample(mask_buffer, mask_sampler, in.texcoord + vec2<f32>(dx, dy)).x;
 
     if samples[1][1] > 0.99 {
+#ifdef EDGE_SEEDS
+        // Only fully covered fragments on the boundary of the mask, or of an
+        // object, are seeds, so that interior fragments receive the distance
+        // to the nearest boundary.
+#ifdef SEPARATE_OBJECTS
+        let id = object_id(in.texcoord);
+#endif
+
+        var offsets: array<vec2<f32>, 8>;
+        offsets[0] = vec2<f32>(-dx, -dy);
+        offsets[1] = vec2<f32>(-dx, 0.0);
+        offsets[2] = vec2<f32>(-dx, dy);
+        offsets[3] = vec2<f32>(0.0, -dy);
+        offsets[4] = vec2<f32>(0.0, dy);
+        offsets[5] = vec2<f32>(dx, -dy);
+        offsets[6] = vec2<f32>(dx, 0.0);
+        offsets[7] = vec2<f32>(dx, dy);
+
+        for (var i: i32 = 0; i < 8; i = i + 1) {
+            let coord = in.texcoord + offsets[i];
+            let neighbor_mask = textureSampleLevel(mask_buffer, mask_sampler, coord, 0.0).x;
+            if neighbor_mask <= 0.99 {
+                return out_position;
+            }
+
+#ifdef SEPARATE_OBJECTS
+            let neighbor_id = object_id(coord);
+            if abs(neighbor_id - id) > 0.5 {
+                return out_position;
+            }
+#endif
+        }
+
+        return vec4<f32>(-1.0, -1.0, 0.0, 1.0);
+#else
         return out_position;
+#endif
     }
 
     if samples[1][1] < 0.01 {
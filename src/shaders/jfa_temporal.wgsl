@@ -0,0 +1,60 @@
+#import outline::fullscreen
+#import outline::dimensions
+
+// Pass merging the previous frame's jump flood into this frame's seeds.
+@group(1) @binding(0)
+var seed_buffer: texture_2d<f32>;
+@group(1) @binding(1)
+var history_buffer: texture_2d<f32>;
+@group(1) @binding(2)
+var src_sampler: sampler;
+
+struct FragmentIn {
+    @location(0) texcoord: vec2<f32>,
+};
+
+@fragment
+fn fragment(in: FragmentIn) -> @location(0) vec4<f32> {
+    let seed = textureSampleLevel(seed_buffer, src_sampler, in.texcoord, 0.0).xy;
+
+    // Seeds are nearest to themselves.
+    if seed.x != -1.0 {
+        return vec4<f32>(seed, 0.0, 1.0);
+    }
+
+    // Framebuffer-space position of the nearest seed in the previous frame.
+    let previous = textureSampleLevel(history_buffer, src_sampler, in.texcoord, 0.0).xy;
+    if previous.x == -1.0 {
+        return vec4<f32>(-1.0, -1.0, 0.0, 1.0);
+    }
+
+    let fb_to_pix = vec2<f32>(dims.width, dims.height);
+    let pix_coord = in.texcoord * fb_to_pix;
+
+    // Seeds only move a few pixels between reused frames (`MAX_MOTION` in
+    // temporal.rs), so the nearest current seed is searched for around the
+    // previous one. The correction passes fix up the rest.
+    let infinity = 0x1.FFFFFp127;
+    var min_dist2: f32 = infinity;
+    var min_dist2_pos: vec2<f32> = vec2<f32>(-1.0, -1.0);
+    for (var dist: f32 = 8.0; dist >= 1.0; dist = dist * 0.5) {
+        for (var x: i32 = -1; x <= 1; x = x + 1) {
+            for (var y: i32 = -1; y <= 1; y = y + 1) {
+                let offset = vec2<f32>(f32(x), f32(y)) * dist / fb_to_pix;
+                let fb_sample = textureSampleLevel(seed_buffer, src_sampler, previous + offset, 0.0).xy;
+                if fb_sample.x == -1.0 {
+                    continue;
+                }
+
+                let delta = pix_coord - fb_sample * fb_to_pix;
+                let dist2 = dot(delta, delta);
+                if dist2 < min_dist2 {
+                    min_dist2 = dist2;
+                    min_dist2_pos = fb_sample;
+                }
+            }
+        }
+    }
+
+    return vec4<f32>(min_dist2_pos, 0.0, 1.0);
+}
@@ -10,22 +10,88 @@ var<uniform> view: View;
 @group(1) @binding(0)
 var<uniform> mesh: Mesh;
 
+struct OutlineMesh {
+    // Nonzero object ID, unique among adjacent outlined meshes.
+    id: u32,
+    // Opacity of the object's outline.
+    alpha: f32,
+};
+
+@group(2) @binding(0)
+var<uniform> outline_mesh: OutlineMesh;
+
+struct Dimensions {
+    width: f32,
+    height: f32,
+    inv_width: f32,
+    inv_height: f32,
+    // Width in pixels of the guard band on each side of the view.
+    overscan: f32,
+};
+
+@group(3) @binding(0)
+var<uniform> dims: Dimensions;
+
 struct Vertex {
     @location(0) position: vec3<f32>,
+#ifdef VERTEX_NORMALS
+    @location(1) normal: vec3<f32>,
+#endif
 };
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
+#ifdef CREASES
+    @location(0) world_normal: vec3<f32>,
+    @location(1) view_depth: f32,
+#endif
 };
 
 @vertex
 fn vertex(vertex: Vertex) -> VertexOutput {
     var out: VertexOutput;
-    out.clip_position = view.view_proj * mesh.model * vec4<f32>(vertex.position, 1.0);
+    let world_position = mesh.model * vec4<f32>(vertex.position, 1.0);
+    out.clip_position = view.view_proj * world_position;
+    // The view only covers the texture inside the guard band.
+    let size = vec2<f32>(dims.width, dims.height);
+    out.clip_position.x *= (size.x - 2.0 * dims.overscan) / size.x;
+    out.clip_position.y *= (size.y - 2.0 * dims.overscan) / size.y;
+#ifdef CREASES
+#ifdef VERTEX_NORMALS
+    out.world_normal = (mesh.inverse_transpose_model * vec4<f32>(vertex.normal, 0.0)).xyz;
+#else
+    // Meshes without normals only have creases where their depth changes.
+    out.world_normal = vec3<f32>(0.0);
+#endif
+    out.view_depth = -(view.inverse_view * world_position).z;
+#endif
     return out;
 }
 
+struct FragmentOutput {
+    @location(0) mask: vec4<f32>,
+#ifdef SEPARATE_OBJECTS
+    @location(1) object_id: vec4<f32>,
+#endif
+#ifdef CREASES
+    @location(2) normal_depth: vec4<f32>,
+#endif
+};
+
 @fragment
-fn fragment() -> @location(0) vec4<f32> {
-    return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+fn fragment(in: VertexOutput) -> FragmentOutput {
+    var out: FragmentOutput;
+    out.mask = vec4<f32>(1.0, outline_mesh.alpha, 0.0, 1.0);
+#ifdef SEPARATE_OBJECTS
+    out.object_id = vec4<f32>(f32(outline_mesh.id), 0.0, 0.0, 1.0);
+#endif
+#ifdef CREASES
+    // Fragments without a surface are left with a zero normal.
+#ifdef VERTEX_NORMALS
+    out.normal_depth = vec4<f32>(normalize(in.world_normal), in.view_depth);
+#else
+    out.normal_depth = vec4<f32>(in.world_normal, in.view_depth);
+#endif
+#endif
+    return out;
 }
@@ -5,6 +5,33 @@ struct Params {
     color: vec4<f32>,
     // Outline weight in pixels.
     weight: f32,
+    // Weight in pixels of lines between adjacent objects.
+    contour_weight: f32,
+    // Distance in pixels over which the edges of the outline fade out.
+    softness: f32,
+    // Shadow color, transparent without a shadow.
+    shadow_color: vec4<f32>,
+    // Shadow offset in pixels.
+    shadow_offset: vec2<f32>,
+    // Distance in pixels over which the shadow fades out.
+    shadow_softness: f32,
+    // Fill color, transparent without a fill.
+    fill_color: vec4<f32>,
+    // Distance in pixels over which the fill fades out from the edge, or zero.
+    fill_inner_distance: f32,
+    // Fill pattern: 0 for solid, 1 for stripes, 2 for dots.
+    fill_pattern: u32,
+    // Pattern repeat distance in pixels.
+    fill_pattern_spacing: f32,
+    // Stripe width or dot radius in pixels.
+    fill_pattern_size: f32,
+    // Crease line color, transparent without creases.
+    crease_color: vec4<f32>,
+    // Crease line weight in pixels.
+    crease_weight: f32,
+    // Change in normal and relative change in depth marking a crease.
+    crease_normal_threshold: f32,
+    crease_depth_threshold: f32,
 };
 
 @group(1) @binding(0)
@@ -13,23 +40,213 @@ var jfa_buffer: texture_2d<f32>;
 var mask_buffer: texture_2d<f32>;
 @group(1) @binding(2)
 var nearest_sampler: sampler;
+@group(1) @binding(3)
+#ifdef MULTISAMPLED_IDS
+var object_id_buffer: texture_multisampled_2d<f32>;
+#else
+var object_id_buffer: texture_2d<f32>;
+#endif
+@group(1) @binding(4)
+var normal_depth_buffer: texture_2d<f32>;
 
 @group(2) @binding(0)
 var<uniform> params: Params;
 
+// Returns the ID of the object covering `coord`.
+//
+// IDs aren't resolved from their samples, since averaging would mix the IDs
+// of adjacent objects, so the first sample is read instead.
+fn object_id(coord: vec2<f32>) -> f32 {
+    let size = vec2<i32>(i32(dims.width), i32(dims.height));
+    let texel = clamp(vec2<i32>(coord * vec2<f32>(size)), vec2<i32>(0), size - 1);
+    return textureLoad(object_id_buffer, texel, 0).x;
+}
+
+// Returns the outline opacity of the object covering `coord`.
+//
+// The mask stores coverage-weighted opacity, so dividing by the coverage
+// recovers the opacity of the object itself.
+fn object_alpha(coord: vec2<f32>) -> f32 {
+    let mask = textureSampleLevel(mask_buffer, nearest_sampler, coord, 0.0).rg;
+    if mask.r > 0.0 {
+        return mask.g / mask.r;
+    }
+
+    return 1.0;
+}
+
 struct FragmentIn {
     @location(0) texcoord: vec2<f32>,
 };
 
-@fragment
-fn fragment(in: FragmentIn) -> @location(0) vec4<f32> {
-    let fb_jfa_pos = textureSample(jfa_buffer, nearest_sampler, in.texcoord).xy;
+#ifdef SEPARATE_OBJECTS
+// Returns whether the seed at `fb_seed` lies on a boundary between two objects,
+// rather than on the outer boundary of the mask.
+fn is_contour_seed(fb_seed: vec2<f32>) -> bool {
+    let id = object_id(fb_seed);
+
+    var offsets: array<vec2<f32>, 4>;
+    offsets[0] = vec2<f32>(-dims.inv_width, 0.0);
+    offsets[1] = vec2<f32>(dims.inv_width, 0.0);
+    offsets[2] = vec2<f32>(0.0, -dims.inv_height);
+    offsets[3] = vec2<f32>(0.0, dims.inv_height);
+
+    for (var i: i32 = 0; i < 4; i = i + 1) {
+        let coord = fb_seed + offsets[i];
+        let neighbor_mask = textureSampleLevel(mask_buffer, nearest_sampler, coord, 0.0).x;
+        let neighbor_id = object_id(coord);
+
+        if neighbor_mask > 0.99 && abs(neighbor_id - id) > 0.5 {
+            return true;
+        }
+    }
+
+    return false;
+}
+#endif
+
+// Returns the shadow opacity at `texcoord`, ignoring the objects casting it.
+fn shadow_alpha(texcoord: vec2<f32>) -> f32 {
+    if params.shadow_color.a <= 0.0 {
+        return 0.0;
+    }
+
+    let fb_to_pix = vec2<f32>(dims.width, dims.height);
+    let coord = texcoord - params.shadow_offset / fb_to_pix;
+    if any(coord < vec2<f32>(0.0)) || any(coord > vec2<f32>(1.0)) {
+        return 0.0;
+    }
+
+    // Interior fragments may not be seeds, when the fill needs the distance
+    // to the edge.
+    let mask_value = textureSampleLevel(mask_buffer, nearest_sampler, coord, 0.0).r;
+    if mask_value >= 1.0 {
+        return params.shadow_color.a * object_alpha(coord);
+    }
+
+    let fb_seed = textureSampleLevel(jfa_buffer, nearest_sampler, coord, 0.0).xy;
+    if fb_seed.x == -1.0 {
+        return 0.0;
+    }
+
+    let mag = distance(coord * fb_to_pix, fb_seed * fb_to_pix);
+    let fade = 1.0 - smoothstep(0.0, max(params.shadow_softness, 1.0), mag);
+    return params.shadow_color.a * object_alpha(fb_seed) * fade;
+}
+
+// Returns the coverage of the fill pattern at `pix_coord`.
+fn fill_pattern(pix_coord: vec2<f32>) -> f32 {
+    let spacing = max(params.fill_pattern_spacing, 1.0);
+
+    switch params.fill_pattern {
+        // Diagonal stripes.
+        case 1u: {
+            let along = (pix_coord.x + pix_coord.y) * 0.70710678;
+            let from_center = abs(fract(along / spacing) - 0.5) * spacing;
+            return clamp(0.5 * params.fill_pattern_size - from_center + 0.5, 0.0, 1.0);
+        }
+        // Dots.
+        case 2u: {
+            let from_center = (fract(pix_coord / spacing) - 0.5) * spacing;
+            return clamp(params.fill_pattern_size - length(from_center) + 0.5, 0.0, 1.0);
+        }
+        default: {
+            return 1.0;
+        }
+    }
+}
+
+// Returns the fill opacity at `texcoord`, ignoring the coverage of the mask.
+fn fill_alpha(texcoord: vec2<f32>) -> f32 {
+    if params.fill_color.a <= 0.0 {
+        return 0.0;
+    }
+
     let fb_to_pix = vec2<f32>(dims.width, dims.height);
+    let pix_coord = texcoord * fb_to_pix;
+    var alpha = params.fill_color.a * object_alpha(texcoord) * fill_pattern(pix_coord);
 
-    let mask_value = textureSample(mask_buffer, nearest_sampler, in.texcoord).r;
+    // Interior fragments receive the distance to the edge of the mask.
+    if params.fill_inner_distance > 0.0 {
+        let fb_seed = textureSampleLevel(jfa_buffer, nearest_sampler, texcoord, 0.0).xy;
+        if fb_seed.x != -1.0 {
+            let mag = distance(pix_coord, fb_seed * fb_to_pix);
+            alpha = alpha * (1.0 - smoothstep(0.0, params.fill_inner_distance, mag));
+        }
+    }
+
+    return alpha;
+}
+
+#ifdef CREASES
+// Returns the opacity of crease lines at `texcoord`, ignoring the coverage of
+// the mask.
+//
+// Creases are found with a Sobel filter over the normals and depths of the
+// outlined surfaces. Samples are taken half the line weight apart, so that
+// lines are about as wide as their weight.
+fn crease_alpha(texcoord: vec2<f32>) -> f32 {
+    if params.crease_color.a <= 0.0 || params.crease_weight <= 0.0 {
+        return 0.0;
+    }
+
+    let center = textureSampleLevel(normal_depth_buffer, nearest_sampler, texcoord, 0.0);
+    if dot(center.xyz, center.xyz) < 0.25 {
+        return 0.0;
+    }
+
+    let reach = max(0.5 * params.crease_weight, 1.0);
+    let offset = reach / vec2<f32>(dims.width, dims.height);
+
+    var samples: array<vec4<f32>, 9>;
+    for (var i: i32 = 0; i < 9; i = i + 1) {
+        let step = vec2<f32>(f32(i / 3 - 1), f32(i % 3 - 1));
+        let sample = textureSampleLevel(normal_depth_buffer, nearest_sampler, texcoord + step * offset, 0.0);
+        // The silhouette is left to the outline, so samples off the surfaces
+        // take the center's value.
+        samples[i] = select(center, sample, dot(sample.xyz, sample.xyz) >= 0.25);
+    }
+
+    let gx = samples[6] + 2.0 * samples[7] + samples[8] - samples[0] - 2.0 * samples[1] - samples[2];
+    let gy = samples[2] + 2.0 * samples[5] + samples[8] - samples[0] - 2.0 * samples[3] - samples[6];
+
+    // Each side of the filter weighs 4 samples.
+    let normal_change = sqrt(dot(gx.xyz, gx.xyz) + dot(gy.xyz, gy.xyz)) / 4.0;
+    let depth_change = sqrt(gx.w * gx.w + gy.w * gy.w) / (4.0 * max(center.w, 0.0001));
+    if normal_change <= params.crease_normal_threshold && depth_change <= params.crease_depth_threshold {
+        return 0.0;
+    }
+
+    // Lines thinner than the filter are faded instead.
+    let thinning = min(params.crease_weight / (2.0 * reach), 1.0);
+    return params.crease_color.a * object_alpha(texcoord) * thinning;
+}
+#endif
+
+// Composites non-premultiplied `top` over `bottom`.
+fn blend_over(top: vec4<f32>, bottom: vec4<f32>) -> vec4<f32> {
+    let alpha = top.a + bottom.a * (1.0 - top.a);
+    if alpha <= 0.0 {
+        return vec4<f32>(0.0, 0.0, 0.0, 0.0);
+    }
+
+    let rgb = top.rgb * top.a + bottom.rgb * bottom.a * (1.0 - top.a);
+    return vec4<f32>(rgb / alpha, alpha);
+}
+
+// Returns the opacity of an edge `distance` pixels away, fading out over the
+// softness of the outline.
+fn edge_fade(distance: f32) -> f32 {
+    return clamp(distance / max(params.softness, 0.0001), 0.0, 1.0);
+}
+
+// Returns the outline color at `texcoord`.
+fn outline(texcoord: vec2<f32>, mask_value: f32) -> vec4<f32> {
+    let fb_jfa_pos = textureSampleLevel(jfa_buffer, nearest_sampler, texcoord, 0.0).xy;
+    let fb_to_pix = vec2<f32>(dims.width, dims.height);
 
     // Fragment position in pixel space.
-    let pix_coord = in.texcoord * fb_to_pix;
+    let pix_coord = texcoord * fb_to_pix;
     // Closest initial fragment in pixel space.
     let pix_jfa_pos = fb_jfa_pos * fb_to_pix;
 
@@ -40,13 +257,54 @@ fn fragment(in: FragmentIn) -> @location(0) vec4<f32> {
     // represent the same position due to storage as fp16, so an epsilon is
     // needed.
     if mask_value < 1.0 {
+        var alpha = params.color.a * object_alpha(fb_jfa_pos);
+        // Partially covered fragments are offset from their seed by the
+        // distance to the edge, unless the edge direction is ambiguous.
+        var edge_distance = mag;
         if mask_value > 0.0 {
-            return vec4<f32>(params.color.rgb, 1.0 - mask_value);
-        } else {
-            let fade = clamp(params.weight - mag, 0.0, 1.0);
-            return vec4<f32>(params.color.rgb, fade);
+            alpha = params.color.a * object_alpha(texcoord);
+            if mag < 0.1 {
+                edge_distance = 1.0 - mask_value;
+            }
         }
-    } else {
-        return vec4<f32>(0.0, 0.0, 0.0, 0.0);
+
+        let fade = min(edge_fade(edge_distance), edge_fade(params.weight - edge_distance));
+        return vec4<f32>(params.color.rgb, fade * alpha);
     }
+
+#ifdef SEPARATE_OBJECTS
+    // Seeds lie on both sides of the boundary between two objects, half a
+    // pixel away from it.
+    if fb_jfa_pos.x != -1.0 && is_contour_seed(fb_jfa_pos) {
+        let alpha = params.color.a * object_alpha(fb_jfa_pos);
+        let fade = edge_fade(0.5 * params.contour_weight - mag);
+        return vec4<f32>(params.color.rgb, fade * alpha);
+    }
+#endif
+
+    return vec4<f32>(0.0, 0.0, 0.0, 0.0);
+}
+
+@fragment
+fn fragment(in: FragmentIn) -> @location(0) vec4<f32> {
+    // The view only covers the textures inside the guard band.
+    let size = vec2<f32>(dims.width, dims.height);
+    let texcoord = (in.texcoord * (size - 2.0 * dims.overscan) + dims.overscan) / size;
+    let mask_value = textureSample(mask_buffer, nearest_sampler, texcoord).r;
+
+    // The shadow is hidden behind the objects and below their outline, and the
+    // fill covers their interior.
+    let shadow = vec4<f32>(
+        params.shadow_color.rgb,
+        shadow_alpha(texcoord) * (1.0 - mask_value),
+    );
+    let fill = vec4<f32>(params.fill_color.rgb, fill_alpha(texcoord) * mask_value);
+    var interior = blend_over(fill, shadow);
+#ifdef CREASES
+    // Crease lines are drawn over the fill.
+    let crease = vec4<f32>(params.crease_color.rgb, crease_alpha(texcoord) * mask_value);
+    interior = blend_over(crease, interior);
+#endif
+
+    return blend_over(outline(texcoord, mask_value), interior);
 }
@@ -0,0 +1,109 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::{
+    prelude::*,
+    render::{render_phase::RenderPhase, texture::CachedTexture},
+};
+
+use crate::{
+    hull::ViewHullDepth,
+    jfa,
+    outline::ViewOutlineParams,
+    resources::ViewOutlineTextures,
+    temporal::{ReuseOutlineFlood, ReuseOutlineHistory},
+    MeshMask, OutlineSettings,
+};
+
+/// Statistics of the outline passes for one view.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ViewOutlineStats {
+    pub view: Entity,
+    /// Number of meshes drawn to the view's mask.
+    pub mesh_masks: usize,
+    /// Number of jump flood iterations run for the view.
+    pub jfa_iterations: usize,
+    /// Size in pixels of the view's jump flood textures.
+    pub jfa_resolution: UVec2,
+}
+
+/// Statistics of the outline passes in the most recently rendered frame.
+#[derive(Clone, Debug, Default, Resource)]
+pub struct OutlineStats {
+    pub views: Vec<ViewOutlineStats>,
+    /// Largest size in pixels of the jump flood textures of any view.
+    pub jfa_resolution: UVec2,
+    /// Bytes allocated for the textures of the outline passes, over all views.
+    pub texture_bytes: u64,
+}
+
+/// Statistics collected by the render world, to be published in the main
+/// world.
+#[derive(Clone, Default, Resource)]
+pub(crate) struct SharedOutlineStats(Arc<Mutex<Option<OutlineStats>>>);
+
+pub(crate) fn update_outline_stats(
+    shared: Res<SharedOutlineStats>,
+    mut stats: ResMut<OutlineStats>,
+) {
+    if let Some(latest) = shared.0.lock().unwrap().take() {
+        *stats = latest;
+    }
+}
+
+type StatsViewQuery = (
+    Entity,
+    &'static RenderPhase<MeshMask>,
+    Option<&'static ViewOutlineParams>,
+    Option<&'static OutlineSettings>,
+    Option<&'static ViewOutlineTextures>,
+    Option<&'static ReuseOutlineHistory>,
+    Option<&'static ReuseOutlineFlood>,
+    Option<&'static ViewHullDepth>,
+);
+
+pub(crate) fn collect_outline_stats(shared: Res<SharedOutlineStats>, views: Query<StatsViewQuery>) {
+    let mut stats = OutlineStats::default();
+    for (view, phase, outline, settings, textures, reuse_history, reuse_flood, hull_depth) in
+        views.iter()
+    {
+        // The jump flood is skipped for views without outline parameters, or
+        // whose flood is unchanged.
+        let jfa_iterations = match (outline, settings, textures) {
+            _ if reuse_flood.is_some() => 0,
+            (Some(outline), Some(settings), Some(textures)) => {
+                jfa::view_jump_exponents(outline, settings, textures, reuse_history.is_some()).len()
+            }
+            _ => 0,
+        };
+        let jfa_resolution = textures.map_or(UVec2::ZERO, |textures| {
+            let jfa = &textures.jfa_primary_output.texture;
+            UVec2::new(jfa.width(), jfa.height())
+        });
+
+        stats.views.push(ViewOutlineStats {
+            view,
+            mesh_masks: if reuse_flood.is_some() {
+                0
+            } else {
+                phase.items.len()
+            },
+            jfa_iterations,
+            jfa_resolution,
+        });
+        stats.jfa_resolution = stats.jfa_resolution.max(jfa_resolution);
+        stats.texture_bytes += textures.map_or(0, |textures| {
+            textures.textures().map(texture_bytes).sum::<u64>()
+        });
+        stats.texture_bytes += hull_depth.map_or(0, |depth| texture_bytes(&depth.0));
+    }
+
+    *shared.0.lock().unwrap() = Some(stats);
+}
+
+// All outline textures use uncompressed formats with a single mip level.
+fn texture_bytes(texture: &CachedTexture) -> u64 {
+    let texture = &texture.texture;
+    let texel_size = texture.format().describe().block_size as u64;
+
+    texture.width() as u64 * texture.height() as u64 * texture.sample_count() as u64 * texel_size
+}
@@ -0,0 +1,316 @@
+use bevy::{
+    pbr::{MeshUniform, SkinnedMeshJoints},
+    prelude::*,
+    render::{
+        primitives::Aabb,
+        render_asset::RenderAssets,
+        render_phase::RenderPhase,
+        render_resource::{BufferId, CachedRenderPipelineId, PipelineCache, TextureId},
+        view::ExtractedView,
+    },
+    utils::HashMap,
+};
+
+use crate::{
+    jfa::{self, JfaPipeline},
+    jfa_init::JfaInitPipeline,
+    outline::ViewOutlineParams,
+    resources::ViewOutlineTextures,
+    ExtractedOutline, MeshMask, OutlineMeshUniform, OutlineSettings,
+};
+
+/// Exponent of the first jump distance of the passes correcting a reused
+/// flood.
+pub(crate) const MAX_JUMP_EXPONENT: usize = 3;
+
+// Greatest distance in pixels outlined objects can move on screen for the
+// previous flood to be reused. The flood isn't reprojected, so
+// jfa_temporal.wgsl only searches this far around the previous seeds, and
+// views moving any faster are flooded from scratch.
+const MAX_MOTION: f32 = 8.0;
+
+/// Marker for views reusing the previous frame's flood.
+#[derive(Component)]
+pub(crate) struct ReuseOutlineHistory;
+
+/// Marker for views whose mask and flood are unchanged since the previous
+/// frame, so only the outline is drawn.
+#[derive(Component)]
+pub(crate) struct ReuseOutlineFlood;
+
+// A view as of its previous flood.
+pub(crate) struct ViewHistory {
+    // Textures read by the outline pass, which must be kept between frames.
+    textures: Vec<TextureId>,
+    history: Option<TextureId>,
+    view_proj: Mat4,
+    settings: OutlineSettings,
+    init_pipeline: CachedRenderPipelineId,
+    max_exp: usize,
+    meshes: HashMap<Entity, MeshHistory>,
+    // Skinned meshes change shape without moving.
+    skinned: bool,
+    // Whether every pass of the flood could run.
+    ready: bool,
+}
+
+#[derive(PartialEq)]
+struct MeshHistory {
+    transform: Mat4,
+    // World space corners of the mesh's bounds, if known.
+    corners: Option<[Vec3; 8]>,
+    // Replaced when the mesh drawn into the mask or its asset changes.
+    vertex_buffer: Option<BufferId>,
+    // ID and alpha written to the mask, which change while fading.
+    uniform: OutlineMeshUniform,
+}
+
+type TemporalViewQuery = (
+    Entity,
+    &'static ExtractedView,
+    &'static RenderPhase<MeshMask>,
+    &'static OutlineSettings,
+    &'static ViewOutlineParams,
+    &'static ViewOutlineTextures,
+);
+
+type TemporalMeshQuery = (
+    &'static MeshUniform,
+    &'static ExtractedOutline,
+    &'static OutlineMeshUniform,
+    Option<&'static SkinnedMeshJoints>,
+);
+
+/// Decides which views can reuse the previous frame's mask and flood.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn prepare_flood_reuse(
+    mut commands: Commands,
+    mut histories: Local<HashMap<Entity, ViewHistory>>,
+    pipeline_cache: Res<PipelineCache>,
+    init_pipeline: Res<JfaInitPipeline>,
+    jfa_pipeline: Res<JfaPipeline>,
+    render_meshes: Res<RenderAssets<Mesh>>,
+    views: Query<TemporalViewQuery>,
+    meshes: Query<TemporalMeshQuery>,
+) {
+    histories.retain(|entity, _| views.contains(*entity));
+
+    for (entity, view, phase, settings, outline, textures) in views.iter() {
+        let init_pipeline = init_pipeline.id(settings, outline.params.needs_inner_distance());
+        let ready = [init_pipeline, jfa_pipeline.id(settings.precision)]
+            .into_iter()
+            .chain(phase.items.iter().map(|item| item.pipeline))
+            .all(|id| pipeline_cache.get_render_pipeline(id).is_some());
+
+        let mut skinned = false;
+        let meshes = phase
+            .items
+            .iter()
+            .filter_map(|item| {
+                let (mesh, outline, uniform, joints) = meshes.get(item.entity).ok()?;
+                skinned |= joints.is_some();
+                let history = MeshHistory {
+                    transform: mesh.transform,
+                    corners: outline
+                        .aabb
+                        .map(|aabb| world_corners(&aabb, mesh.transform)),
+                    vertex_buffer: render_meshes
+                        .get(&outline.mesh)
+                        .map(|mesh| mesh.vertex_buffer.id()),
+                    uniform: uniform.clone(),
+                };
+                Some((item.entity, history))
+            })
+            .collect();
+
+        let mut current = ViewHistory {
+            textures: [&textures.mask_output, &textures.jfa_final_output]
+                .into_iter()
+                .chain(&textures.object_id_output)
+                .chain(&textures.normal_depth_output)
+                .map(|texture| texture.texture.id())
+                .collect(),
+            history: textures
+                .jfa_history
+                .as_ref()
+                .map(|history| history.texture.id()),
+            view_proj: view
+                .view_projection
+                .unwrap_or_else(|| view.projection * view.transform.compute_matrix().inverse()),
+            settings: settings.clone(),
+            init_pipeline,
+            max_exp: jfa::view_max_jump_exponent(outline, settings, textures),
+            meshes,
+            skinned,
+            ready,
+        };
+
+        let size = Vec2::new(view.viewport.z as f32, view.viewport.w as f32);
+        match histories.get(&entity) {
+            Some(previous) if previous.unchanged(&current) => {
+                // The kept flood still reaches as far as it did.
+                current.max_exp = previous.max_exp;
+                commands.entity(entity).insert(ReuseOutlineFlood);
+            }
+            Some(previous) if previous.can_reuse(&current, size) => {
+                commands.entity(entity).insert(ReuseOutlineHistory);
+            }
+            _ => {}
+        }
+
+        histories.insert(entity, current);
+    }
+}
+
+impl ViewHistory {
+    fn unchanged(&self, current: &ViewHistory) -> bool {
+        self.ready
+            && !current.skinned
+            && self.textures == current.textures
+            && self.view_proj == current.view_proj
+            && self.settings == current.settings
+            && self.init_pipeline == current.init_pipeline
+            && self.max_exp >= current.max_exp
+            && self.meshes == current.meshes
+    }
+
+    fn can_reuse(&self, current: &ViewHistory, size: Vec2) -> bool {
+        // A new history texture is empty, and a longer flood reaches further
+        // than the correction passes.
+        if !self.ready
+            || !current.settings.temporal
+            || self.history != current.history
+            || self.max_exp < current.max_exp
+        {
+            return false;
+        }
+
+        // Objects appearing or moving far would have to be flooded from
+        // scratch, and those disappearing would leave their seeds behind.
+        self.meshes.len() == current.meshes.len()
+            && current.meshes.iter().all(|(entity, mesh)| {
+                let Some(previous) = self.meshes.get(entity) else {
+                    return false;
+                };
+                let (Some(previous), Some(current_corners)) = (previous.corners, mesh.corners)
+                else {
+                    return false;
+                };
+
+                // Every corner must stay close for the silhouette to.
+                previous
+                    .iter()
+                    .zip(&current_corners)
+                    .all(|(&previous, &corner)| {
+                        match (
+                            screen_position(self.view_proj, previous, size),
+                            screen_position(current.view_proj, corner, size),
+                        ) {
+                            (Some(previous), Some(current)) => {
+                                previous.distance(current) <= MAX_MOTION
+                            }
+                            _ => false,
+                        }
+                    })
+            })
+    }
+}
+
+fn world_corners(aabb: &Aabb, transform: Mat4) -> [Vec3; 8] {
+    let (center, half_extents) = (Vec3::from(aabb.center), Vec3::from(aabb.half_extents));
+    [0, 1, 2, 3, 4, 5, 6, 7].map(|i| {
+        let sign = Vec3::new(
+            if i & 1 == 0 { -1.0 } else { 1.0 },
+            if i & 2 == 0 { -1.0 } else { 1.0 },
+            if i & 4 == 0 { -1.0 } else { 1.0 },
+        );
+        transform.transform_point3(center + sign * half_extents)
+    })
+}
+
+// Projects `position` to pixels from the center of the view, if it's in front
+// of the camera.
+fn screen_position(view_proj: Mat4, position: Vec3, size: Vec2) -> Option<Vec2> {
+    let clip = view_proj * position.extend(1.0);
+    (clip.w > 0.0).then(|| clip.truncate().truncate() / clip.w * 0.5 * size)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_4;
+
+    use super::*;
+
+    const SIZE: Vec2 = Vec2::new(800.0, 600.0);
+
+    // Unit cube at `translation`, outlined at `alpha`.
+    fn mesh(translation: Vec3, alpha: f32) -> MeshHistory {
+        let transform = Mat4::from_translation(translation);
+        let aabb = Aabb::from_min_max(Vec3::splat(-0.5), Vec3::splat(0.5));
+        MeshHistory {
+            transform,
+            corners: Some(world_corners(&aabb, transform)),
+            vertex_buffer: None,
+            uniform: OutlineMeshUniform::new(0, alpha),
+        }
+    }
+
+    // View from 10 units along +Z of the given meshes, each with its own
+    // entity.
+    fn view(meshes: impl IntoIterator<Item = MeshHistory>) -> ViewHistory {
+        let projection = Mat4::perspective_rh(FRAC_PI_4, SIZE.x / SIZE.y, 0.1, 100.0);
+        let view = Mat4::look_at_rh(Vec3::Z * 10.0, Vec3::ZERO, Vec3::Y);
+        ViewHistory {
+            textures: Vec::new(),
+            history: None,
+            view_proj: projection * view,
+            settings: OutlineSettings {
+                temporal: true,
+                ..default()
+            },
+            init_pipeline: CachedRenderPipelineId::INVALID,
+            max_exp: 4,
+            meshes: meshes
+                .into_iter()
+                .enumerate()
+                .map(|(index, mesh)| (Entity::from_raw(index as u32), mesh))
+                .collect(),
+            skinned: false,
+            ready: true,
+        }
+    }
+
+    #[test]
+    fn unchanged_requires_the_same_mask() {
+        let previous = view([mesh(Vec3::ZERO, 1.0)]);
+        assert!(previous.unchanged(&view([mesh(Vec3::ZERO, 1.0)])));
+
+        // A fading outline is drawn into the mask with a new alpha.
+        assert!(!previous.unchanged(&view([mesh(Vec3::ZERO, 0.5)])));
+        assert!(!previous.unchanged(&view([mesh(Vec3::X * 0.01, 1.0)])));
+        assert!(!previous.unchanged(&view([])));
+    }
+
+    #[test]
+    fn can_reuse_small_motions() {
+        let previous = view([mesh(Vec3::ZERO, 1.0)]);
+        assert!(previous.can_reuse(&view([mesh(Vec3::X * 0.01, 0.5)]), SIZE));
+        assert!(!previous.can_reuse(&view([mesh(Vec3::X, 1.0)]), SIZE));
+
+        // Moving towards the camera grows the silhouette without moving its
+        // center.
+        assert!(!previous.can_reuse(&view([mesh(Vec3::Z * 2.0, 1.0)]), SIZE));
+    }
+
+    #[test]
+    fn can_reuse_requires_the_same_meshes() {
+        let previous = view([mesh(Vec3::ZERO, 1.0)]);
+        let appeared = view([mesh(Vec3::ZERO, 1.0), mesh(Vec3::X, 1.0)]);
+        assert!(!previous.can_reuse(&appeared, SIZE));
+        assert!(!appeared.can_reuse(&previous, SIZE));
+
+        let mut unbounded = mesh(Vec3::ZERO, 1.0);
+        unbounded.corners = None;
+        assert!(!previous.can_reuse(&view([unbounded]), SIZE));
+    }
+}
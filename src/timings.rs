@@ -0,0 +1,92 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use bevy::{prelude::*, render::renderer::RenderContext};
+
+/// A pass of the outline render graph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OutlinePass {
+    /// Rendering outlined meshes into the mask.
+    Mask,
+    /// Seeding the jump flood from the mask.
+    JfaInit,
+    /// Merging the previous frame's jump flood into the seeds.
+    JfaTemporal,
+    /// A jump flood iteration with the given jump distance in pixels.
+    Jfa(u32),
+    /// Compositing the outline onto the view.
+    Outline,
+    /// Drawing hull outlines onto the view.
+    Hull,
+}
+
+/// GPU time spent in one outline pass for one view.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutlinePassTiming {
+    pub view: Entity,
+    pub pass: OutlinePass,
+    pub duration: Duration,
+}
+
+/// GPU timings of the outline passes in the most recently measured frame.
+///
+/// Timings are only recorded with the `gpu-timings` feature, on devices
+/// supporting `TIMESTAMP_QUERY`. They are read back asynchronously, so they
+/// lag a few frames behind rendering.
+#[derive(Clone, Debug, Default, Resource)]
+pub struct OutlineGpuTimings {
+    pub passes: Vec<OutlinePassTiming>,
+}
+
+impl OutlineGpuTimings {
+    /// Returns the total time spent in passes matching `filter`, over all
+    /// views.
+    pub fn total(&self, mut filter: impl FnMut(OutlinePass) -> bool) -> Duration {
+        self.passes
+            .iter()
+            .filter(|timing| filter(timing.pass))
+            .map(|timing| timing.duration)
+            .sum()
+    }
+}
+
+/// Timings read back by the render world, to be published in the main world.
+#[derive(Clone, Default, Resource)]
+pub(crate) struct SharedGpuTimings(pub(crate) Arc<Mutex<Option<Vec<OutlinePassTiming>>>>);
+
+pub(crate) fn update_gpu_timings(
+    shared: Res<SharedGpuTimings>,
+    mut timings: ResMut<OutlineGpuTimings>,
+) {
+    if let Some(passes) = shared.0.lock().unwrap().take() {
+        timings.passes = passes;
+    }
+}
+
+/// Runs `f`, timing the passes it encodes as `pass` for `view`.
+pub(crate) fn time_pass<R>(
+    world: &World,
+    render_context: &mut RenderContext,
+    view: Entity,
+    pass: OutlinePass,
+    f: impl FnOnce(&mut RenderContext) -> R,
+) -> R {
+    #[cfg(feature = "gpu-timings")]
+    {
+        let timer = world.resource::<crate::gpu_timer::GpuTimer>();
+        let scope = timer.begin(render_context, view, pass);
+        let result = f(render_context);
+        if let Some(scope) = scope {
+            timer.end(render_context, scope);
+        }
+        result
+    }
+
+    #[cfg(not(feature = "gpu-timings"))]
+    {
+        let _ = (world, view, pass);
+        f(render_context)
+    }
+}
@@ -0,0 +1,221 @@
+use std::time::Duration;
+
+use bevy::{ecs::reflect::ReflectComponent, prelude::*, render::Extract};
+
+use crate::{outline::OutlineParams, CameraOutline, Outline, OutlineStyle};
+
+/// Easing curve for outline transitions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect, FromReflect)]
+pub enum OutlineEasing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    #[default]
+    EaseInOut,
+}
+
+impl OutlineEasing {
+    /// Maps linear progress `t` in `[0, 1]` onto the easing curve.
+    pub fn ease(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+
+        match self {
+            OutlineEasing::Linear => t,
+            OutlineEasing::EaseIn => t * t,
+            OutlineEasing::EaseOut => t * (2.0 - t),
+            OutlineEasing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// Component for animating changes to outlines.
+///
+/// On a camera, changing [`CameraOutline::style`] blends the color, opacity
+/// and widths from the current style to the new one. On an entity with an
+/// [`Outline`], toggling [`Outline::enabled`] fades the outline in or out,
+/// including on descendants inheriting the outline.
+#[derive(Clone, Debug, PartialEq, Component, Reflect, FromReflect)]
+#[reflect(Component, Default)]
+pub struct OutlineTransition {
+    pub duration: Duration,
+    pub easing: OutlineEasing,
+}
+
+impl Default for OutlineTransition {
+    fn default() -> Self {
+        Self {
+            duration: Duration::from_millis(200),
+            easing: OutlineEasing::default(),
+        }
+    }
+}
+
+impl OutlineTransition {
+    // Progress in `[0, 1]` after `elapsed`.
+    fn progress(&self, elapsed: Duration) -> f32 {
+        if self.duration.is_zero() {
+            1.0
+        } else {
+            (elapsed.as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+        }
+    }
+}
+
+/// Style blend state for a camera with an [`OutlineTransition`].
+#[derive(Clone, Debug, Component)]
+pub(crate) struct OutlineStyleBlend {
+    from: OutlineParams,
+    target: Handle<OutlineStyle>,
+    elapsed: Duration,
+}
+
+impl OutlineStyleBlend {
+    /// Returns the blended parameters at the current point of `transition`.
+    pub(crate) fn current(
+        &self,
+        transition: &OutlineTransition,
+        styles: &Assets<OutlineStyle>,
+    ) -> OutlineParams {
+        match styles.get(&self.target) {
+            Some(style) => self.from.lerp(
+                &OutlineParams::from_style(style),
+                transition.easing.ease(transition.progress(self.elapsed)),
+            ),
+            None => self.from.clone(),
+        }
+    }
+}
+
+/// Render world blend state for a camera in the middle of a style transition.
+///
+/// The target parameters are taken from the prepared [`OutlineStyle`], so the
+/// blend is finished on the render side.
+#[derive(Clone, Debug, Component)]
+pub(crate) struct ExtractedStyleBlend {
+    pub(crate) from: OutlineParams,
+    pub(crate) t: f32,
+}
+
+/// Fade state for an entity with an [`OutlineTransition`].
+#[derive(Clone, Debug, Component)]
+pub(crate) struct OutlineFade {
+    progress: f32,
+    alpha: f32,
+}
+
+impl OutlineFade {
+    pub(crate) fn alpha(&self) -> f32 {
+        self.alpha
+    }
+}
+
+pub(crate) fn update_style_blends(
+    mut commands: Commands,
+    time: Res<Time>,
+    styles: Res<Assets<OutlineStyle>>,
+    mut cameras: Query<(
+        Entity,
+        &CameraOutline,
+        &OutlineTransition,
+        Option<&mut OutlineStyleBlend>,
+    )>,
+) {
+    for (entity, outline, transition, blend) in cameras.iter_mut() {
+        let Some(mut blend) = blend else {
+            // The initial style is shown as is.
+            let from = styles
+                .get(&outline.style)
+                .map(OutlineParams::from_style)
+                .unwrap_or_default();
+            commands.entity(entity).insert(OutlineStyleBlend {
+                from,
+                target: outline.style.clone(),
+                elapsed: transition.duration,
+            });
+            continue;
+        };
+
+        if blend.target != outline.style {
+            let from = blend.current(transition, &styles);
+            *blend = OutlineStyleBlend {
+                from,
+                target: outline.style.clone(),
+                elapsed: Duration::ZERO,
+            };
+        } else if blend.elapsed < transition.duration {
+            blend.elapsed = (blend.elapsed + time.delta()).min(transition.duration);
+        }
+    }
+}
+
+pub(crate) fn update_outline_fades(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut outlines: Query<(
+        Entity,
+        &Outline,
+        &OutlineTransition,
+        Option<&mut OutlineFade>,
+    )>,
+) {
+    for (entity, outline, transition, fade) in outlines.iter_mut() {
+        let target = if outline.enabled { 1.0 } else { 0.0 };
+
+        let Some(mut fade) = fade else {
+            // Newly added outlines fade in.
+            commands.entity(entity).insert(OutlineFade {
+                progress: 0.0,
+                alpha: 0.0,
+            });
+            continue;
+        };
+
+        if fade.progress == target {
+            continue;
+        }
+
+        let step = if transition.duration.is_zero() {
+            1.0
+        } else {
+            time.delta_seconds() / transition.duration.as_secs_f32()
+        };
+
+        let progress = if target > fade.progress {
+            (fade.progress + step).min(target)
+        } else {
+            (fade.progress - step).max(target)
+        };
+
+        *fade = OutlineFade {
+            progress,
+            alpha: transition.easing.ease(progress),
+        };
+    }
+}
+
+/// Removes fade state from entities that no longer have a transition.
+pub(crate) fn remove_outline_fades(
+    mut commands: Commands,
+    mut removed: RemovedComponents<OutlineTransition>,
+    fades: Query<(), With<OutlineFade>>,
+) {
+    for entity in removed.iter() {
+        if fades.contains(entity) {
+            commands.entity(entity).remove::<OutlineFade>();
+        }
+    }
+}
+
+pub(crate) fn extract_style_blends(
+    mut commands: Commands,
+    cameras: Extract<Query<(Entity, &OutlineStyleBlend, &OutlineTransition)>>,
+) {
+    for (entity, blend, transition) in cameras.iter() {
+        if blend.elapsed < transition.duration {
+            commands.get_or_spawn(entity).insert(ExtractedStyleBlend {
+                from: blend.from.clone(),
+                t: transition.easing.ease(transition.progress(blend.elapsed)),
+            });
+        }
+    }
+}
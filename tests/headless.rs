@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use bevy::{asset::AssetPlugin, prelude::*};
+use bevy_jfa::{
+    CameraOutline, InheritedOutline, Outline, OutlinePlugin, OutlinePropagation, OutlineSettings,
+    OutlineStyle, OutlineTransition,
+};
+
+/// Spawns an outlined hierarchy, returning the root and its child.
+fn spawn_outlined(app: &mut App) -> (Entity, Entity) {
+    let child = app.world.spawn_empty().id();
+    let root = app
+        .world
+        .spawn((
+            Outline { enabled: true },
+            OutlinePropagation::Descendants,
+            OutlineTransition {
+                duration: Duration::from_millis(100),
+                ..default()
+            },
+        ))
+        .push_children(&[child])
+        .id();
+
+    (root, child)
+}
+
+#[test]
+fn bare_app() {
+    let mut app = App::new();
+    app.add_plugin(OutlinePlugin::default());
+
+    let (root, child) = spawn_outlined(&mut app);
+    app.update();
+
+    assert!(app.world.contains_resource::<OutlineSettings>());
+    assert!(!app.world.contains_resource::<Assets<OutlineStyle>>());
+    assert_eq!(
+        app.world.get::<InheritedOutline>(child).map(|i| i.source()),
+        Some(root)
+    );
+}
+
+#[test]
+fn minimal_plugins() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(OutlinePlugin::default());
+
+    let (root, child) = spawn_outlined(&mut app);
+    app.world
+        .spawn((CameraOutline::default(), OutlineTransition::default()));
+    for _ in 0..3 {
+        app.update();
+    }
+
+    let inherited = app.world.get::<InheritedOutline>(child).unwrap();
+    assert_eq!(inherited.source(), root);
+    assert!(inherited.enabled());
+}
+
+#[test]
+fn minimal_plugins_with_assets() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(AssetPlugin::default())
+        .add_plugin(OutlinePlugin::default());
+
+    let style = app
+        .world
+        .resource_mut::<Assets<OutlineStyle>>()
+        .add(OutlineStyle::default());
+    let camera = app
+        .world
+        .spawn((
+            CameraOutline {
+                style: style.clone(),
+                ..default()
+            },
+            OutlineTransition::default(),
+        ))
+        .id();
+    spawn_outlined(&mut app);
+    for _ in 0..3 {
+        app.update();
+    }
+
+    let outline = app.world.get::<CameraOutline>(camera).unwrap();
+    assert_eq!(outline.style, style);
+    assert!(app
+        .world
+        .resource::<Assets<OutlineStyle>>()
+        .contains(&style));
+}
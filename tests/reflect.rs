@@ -0,0 +1,196 @@
+use bevy::{
+    asset::{AssetPlugin, HandleId},
+    ecs::entity::EntityMap,
+    prelude::*,
+    reflect::serde::{ReflectSerializer, UntypedReflectDeserializer},
+    render::view::RenderLayers,
+    scene::{serde::SceneDeserializer, DynamicScene},
+    time::TimePlugin,
+};
+use bevy_jfa::{
+    CameraOutline, InheritedOutline, Outline, OutlineCreases, OutlineFill, OutlineFillPattern,
+    OutlineMethod, OutlinePlugin, OutlinePrecision, OutlinePropagation, OutlineProxy,
+    OutlineQuality, OutlineSettings, OutlineShadow, OutlineStyle,
+};
+use serde::de::DeserializeSeed;
+
+fn test_app() -> App {
+    let mut app = App::new();
+    app.add_plugin(TimePlugin)
+        .add_plugin(AssetPlugin::default())
+        .add_asset::<Shader>()
+        .register_type::<Entity>()
+        .register_type::<Color>()
+        .register_type::<Vec2>()
+        .register_type::<RenderLayers>()
+        .register_type::<Handle<Mesh>>()
+        .add_plugin(OutlinePlugin::default());
+    app
+}
+
+/// Serializes `value` to RON and deserializes it back through the registry.
+fn round_trip<T: FromReflect>(app: &App, value: &T) -> T {
+    let registry = app.world.resource::<AppTypeRegistry>().read();
+
+    let serializer = ReflectSerializer::new(value, &registry);
+    let ron = ron::to_string(&serializer).unwrap();
+
+    let mut deserializer = ron::de::Deserializer::from_str(&ron).unwrap();
+    let reflected = UntypedReflectDeserializer::new(&registry)
+        .deserialize(&mut deserializer)
+        .unwrap();
+
+    T::from_reflect(reflected.as_ref()).unwrap()
+}
+
+#[test]
+fn style_round_trip() {
+    let app = test_app();
+    let style = OutlineStyle {
+        color: Color::rgba(0.25, 0.5, 0.75, 0.5),
+        width: 12.0,
+        contour_width: 3.0,
+        softness: 1.5,
+        shadow: Some(OutlineShadow {
+            color: Color::rgba(0.0, 0.0, 0.0, 0.25),
+            offset: Vec2::new(2.0, -3.0),
+            softness: 6.0,
+        }),
+        fill: Some(OutlineFill {
+            color: Color::rgba(1.0, 1.0, 0.0, 0.5),
+            inner_distance: 4.0,
+            pattern: OutlineFillPattern::Stripes {
+                spacing: 8.0,
+                width: 2.0,
+            },
+        }),
+        creases: Some(OutlineCreases {
+            color: Color::rgba(0.0, 0.0, 0.0, 0.75),
+            width: 2.0,
+            normal_threshold: 0.25,
+            depth_threshold: 0.05,
+        }),
+    };
+
+    assert_eq!(round_trip(&app, &style), style);
+}
+
+#[test]
+fn settings_round_trip() {
+    let app = test_app();
+    let mut settings = OutlineSettings::default();
+    settings.set_resolution_scale(0.25);
+    settings.set_msaa(false);
+    settings.set_quality(OutlineQuality::High);
+    settings.set_precision(OutlinePrecision::High);
+    settings.set_separate_objects(true);
+    settings.set_creases(true);
+    settings.set_method(OutlineMethod::Hull);
+    settings.set_overscan(true);
+
+    let result = round_trip(&app, &settings);
+    assert_eq!(result.resolution_scale(), 0.25);
+    assert!(!result.msaa());
+    assert_eq!(result.quality(), OutlineQuality::High);
+    assert_eq!(result.precision(), OutlinePrecision::High);
+    assert!(result.separate_objects());
+    assert!(result.creases());
+    assert_eq!(result.method(), OutlineMethod::Hull);
+    assert!(result.overscan());
+}
+
+#[test]
+fn camera_outline_round_trip() {
+    let mut app = test_app();
+    let style = app
+        .world
+        .resource_mut::<Assets<OutlineStyle>>()
+        .add(OutlineStyle::default());
+    let outline = CameraOutline {
+        enabled: false,
+        style,
+        layers: RenderLayers::layer(3),
+    };
+
+    let result = round_trip(&app, &outline);
+    assert!(!result.enabled);
+    assert_eq!(result.style.id(), outline.style.id());
+    assert_eq!(result.layers, outline.layers);
+}
+
+#[test]
+fn scene_round_trip() {
+    let mut app = test_app();
+    let style = app
+        .world
+        .resource_mut::<Assets<OutlineStyle>>()
+        .add(OutlineStyle::default());
+
+    let camera = app
+        .world
+        .spawn(CameraOutline {
+            enabled: true,
+            style: style.clone(),
+            layers: RenderLayers::layer(1).with(2),
+        })
+        .id();
+    let proxy = Handle::<Mesh>::weak(HandleId::random::<Mesh>());
+    let child = app.world.spawn_empty().id();
+    let root = app
+        .world
+        .spawn((
+            Outline { enabled: false },
+            OutlinePropagation::Descendants,
+            OutlineProxy(proxy.clone()),
+        ))
+        .push_children(&[child])
+        .id();
+    app.update();
+
+    let registry = app.world.resource::<AppTypeRegistry>().clone();
+    let ron = DynamicScene::from_world(&app.world, &registry)
+        .serialize_ron(&registry)
+        .unwrap();
+
+    let mut deserializer = ron::de::Deserializer::from_str(&ron).unwrap();
+    let scene = SceneDeserializer {
+        type_registry: &registry.read(),
+    }
+    .deserialize(&mut deserializer)
+    .unwrap();
+
+    let mut other = test_app();
+    let mut entity_map = EntityMap::default();
+    scene
+        .write_to_world(&mut other.world, &mut entity_map)
+        .unwrap();
+
+    let camera = entity_map.get(camera).unwrap();
+    let root = entity_map.get(root).unwrap();
+    let child = entity_map.get(child).unwrap();
+
+    let camera_outline = other.world.get::<CameraOutline>(camera).unwrap();
+    assert!(camera_outline.enabled);
+    assert_eq!(camera_outline.style.id(), style.id());
+    assert_eq!(camera_outline.layers, RenderLayers::layer(1).with(2));
+
+    assert_eq!(
+        other.world.get::<Outline>(root),
+        Some(&Outline { enabled: false })
+    );
+    assert_eq!(
+        other.world.get::<OutlinePropagation>(root),
+        Some(&OutlinePropagation::Descendants)
+    );
+    assert_eq!(
+        other
+            .world
+            .get::<OutlineProxy>(root)
+            .map(|proxy| proxy.0.id()),
+        Some(proxy.id())
+    );
+
+    let inherited = other.world.get::<InheritedOutline>(child).unwrap();
+    assert_eq!(inherited.source(), root);
+    assert!(!inherited.enabled());
+}